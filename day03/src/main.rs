@@ -1,36 +1,96 @@
-use std::collections::HashSet;
-
-fn get_priority(chr: u8) -> u32 {
-    if (97..=122).contains(&chr) {
-        (chr - 96) as u32
-    } else if (65..=90).contains(&chr) {
-        (chr - 38) as u32
-    } else {
-        panic!("invalid chr")
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+
+#[derive(Debug, PartialEq, Eq)]
+struct UnknownItem {
+    item: char,
+    line: usize,
+}
+
+impl Display for UnknownItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}: item {:?} has no priority in the alphabet",
+            self.line, self.item
+        )
     }
 }
 
-fn solve1(input: &[&str]) -> u32 {
-    let mut tot_priority = 0;
-    for line in input {
-        let input = line.as_bytes();
-        let left: HashSet<u8> = HashSet::from_iter(input[..input.len() / 2].iter().copied());
-        let right: HashSet<u8> = HashSet::from_iter(input[input.len() / 2..].iter().copied());
-        for chr in left.intersection(&right) {
-            tot_priority += get_priority(*chr);
+impl std::error::Error for UnknownItem {}
+
+#[derive(Debug, Clone)]
+struct Priorities {
+    table: HashMap<char, u32>,
+}
+
+impl Priorities {
+    fn new() -> Self {
+        Self {
+            table: HashMap::new(),
+        }
+    }
+
+    /// Assign consecutive priorities to every item in `items`, beginning at `first`
+    fn with_range(mut self, items: RangeInclusive<char>, first: u32) -> Self {
+        for (priority, item) in (first..).zip(items) {
+            self = self.with_item(item, priority);
         }
+        self
+    }
+
+    fn with_item(mut self, item: char, priority: u32) -> Self {
+        self.table.insert(item, priority);
+        self
     }
 
-    tot_priority
+    fn get(&self, item: char) -> Option<u32> {
+        self.table.get(&item).copied()
+    }
 }
 
-fn solve2(input: &[&str]) -> u32 {
+impl Default for Priorities {
+    fn default() -> Self {
+        Self::new()
+            .with_range('a'..='z', 1)
+            .with_range('A'..='Z', 27)
+    }
+}
+
+fn sum_priorities(
+    priorities: &Priorities,
+    groups: impl Iterator<Item = (usize, HashSet<char>)>,
+) -> Result<u32, UnknownItem> {
     let mut tot_priority = 0;
-    for group in input.chunks(3) {
-        let mut common_items: Option<HashSet<u8>> = None;
+    for (line, common) in groups {
+        for item in common {
+            tot_priority += priorities.get(item).ok_or(UnknownItem { item, line })?;
+        }
+    }
+
+    Ok(tot_priority)
+}
+
+fn solve1_with(input: &[&str], priorities: &Priorities) -> Result<u32, UnknownItem> {
+    let groups = input.iter().enumerate().map(|(i, line)| {
+        let items: Vec<char> = line.chars().collect();
+        let left: HashSet<char> = HashSet::from_iter(items[..items.len() / 2].iter().copied());
+        let right: HashSet<char> = HashSet::from_iter(items[items.len() / 2..].iter().copied());
+        (
+            i + 1,
+            HashSet::from_iter(left.intersection(&right).copied()),
+        )
+    });
+
+    sum_priorities(priorities, groups)
+}
+
+fn solve2_with(input: &[&str], priorities: &Priorities) -> Result<u32, UnknownItem> {
+    let groups = input.chunks(3).enumerate().map(|(i, group)| {
+        let mut common_items: Option<HashSet<char>> = None;
         for line in group {
-            let input = line.as_bytes();
-            let pack: HashSet<u8> = HashSet::from_iter(input.iter().copied());
+            let pack: HashSet<char> = HashSet::from_iter(line.chars());
             if let Some(common) = &mut common_items {
                 *common = HashSet::from_iter(common.intersection(&pack).copied());
             } else {
@@ -41,12 +101,18 @@ fn solve2(input: &[&str]) -> u32 {
             panic!("nothing in common");
         };
 
-        for chr in common {
-            tot_priority += get_priority(chr);
-        }
-    }
+        (i * 3 + 1, common)
+    });
+
+    sum_priorities(priorities, groups)
+}
 
-    tot_priority
+fn solve1(input: &[&str]) -> u32 {
+    solve1_with(input, &Priorities::default()).unwrap()
+}
+
+fn solve2(input: &[&str]) -> u32 {
+    solve2_with(input, &Priorities::default()).unwrap()
 }
 
 fn main() {
@@ -78,4 +144,22 @@ mod tests {
     fn test2() {
         assert_eq!(solve2(INPUT), 70)
     }
+
+    #[test]
+    fn test_extended_alphabet() {
+        let priorities = Priorities::default()
+            .with_range('0'..='9', 53)
+            .with_item('ä', 100);
+        assert_eq!(solve1_with(&["a1b1"], &priorities), Ok(54));
+        assert_eq!(solve1_with(&["äxyä"], &priorities), Ok(100));
+        assert_eq!(solve2_with(&["1a", "1b", "1c"], &priorities), Ok(54));
+    }
+
+    #[test]
+    fn test_unknown_item() {
+        assert_eq!(
+            solve1_with(&["abab", "a1b1"], &Priorities::default()),
+            Err(UnknownItem { item: '1', line: 2 })
+        );
+    }
 }
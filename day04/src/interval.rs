@@ -0,0 +1,235 @@
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+
+/// A set of integers stored as sorted, disjoint and non-adjacent inclusive ranges
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+    ranges: Vec<RangeInclusive<i64>>,
+}
+
+impl IntervalSet {
+    pub fn from_ranges<I: IntoIterator<Item = RangeInclusive<i64>>>(ranges: I) -> Self {
+        let mut ranges: Vec<RangeInclusive<i64>> =
+            ranges.into_iter().filter(|r| !r.is_empty()).collect();
+        ranges.sort_unstable_by_key(|r| *r.start());
+
+        // merge overlapping and touching ranges
+        let mut merged: Vec<RangeInclusive<i64>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            if let Some(last) = merged.last_mut() {
+                if *range.start() <= last.end().saturating_add(1) {
+                    *last = *last.start()..=*last.end().max(range.end());
+                    continue;
+                }
+            }
+            merged.push(range);
+        }
+
+        Self { ranges: merged }
+    }
+
+    #[allow(dead_code)]
+    pub fn insert(&mut self, range: RangeInclusive<i64>) {
+        *self = self.union(&Self::from_ranges([range]));
+    }
+
+    pub fn ranges(&self) -> &[RangeInclusive<i64>] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Number of integers covered by the set
+    pub fn len(&self) -> u64 {
+        self.ranges
+            .iter()
+            .map(|r| r.end().abs_diff(*r.start()) + 1)
+            .sum()
+    }
+
+    #[allow(dead_code)]
+    pub fn contains(&self, value: i64) -> bool {
+        let idx = self.ranges.partition_point(|r| *r.end() < value);
+        self.ranges.get(idx).is_some_and(|r| r.contains(&value))
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.difference(other).is_empty()
+    }
+
+    #[allow(dead_code)]
+    pub fn union(&self, other: &Self) -> Self {
+        Self::from_ranges(self.ranges.iter().chain(other.ranges.iter()).cloned())
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut result = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let left = &self.ranges[i];
+            let right = &other.ranges[j];
+            let start = *left.start().max(right.start());
+            let end = *left.end().min(right.end());
+            if start <= end {
+                result.push(start..=end);
+            }
+            if left.end() < right.end() {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        Self { ranges: result }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut result = Vec::new();
+        let mut j = 0;
+        for range in &self.ranges {
+            while j < other.ranges.len() && other.ranges[j].end() < range.start() {
+                j += 1;
+            }
+
+            let mut start = Some(*range.start());
+            for cut in other.ranges[j..]
+                .iter()
+                .take_while(|cut| cut.start() <= range.end())
+            {
+                let Some(cur) = start else {
+                    break;
+                };
+                if *cut.start() > cur {
+                    result.push(cur..=*cut.start() - 1);
+                }
+                start = if cut.end() < range.end() {
+                    Some(*cut.end() + 1)
+                } else {
+                    None
+                };
+            }
+            if let Some(cur) = start {
+                result.push(cur..=*range.end());
+            }
+        }
+
+        Self { ranges: result }
+    }
+}
+
+impl Display for IntervalSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, range) in self.ranges.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            if range.start() == range.end() {
+                write!(f, "{}", range.start())?;
+            } else {
+                write!(f, "{}-{}", range.start(), range.end())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Split the span of `ranges` into consecutive segments tagged with how many ranges cover them
+pub fn coverage(ranges: &[RangeInclusive<i64>]) -> Vec<(RangeInclusive<i64>, usize)> {
+    let mut events: Vec<(i64, isize)> = Vec::with_capacity(ranges.len() * 2);
+    for range in ranges.iter().filter(|r| !r.is_empty()) {
+        events.push((*range.start(), 1));
+        if let Some(after) = range.end().checked_add(1) {
+            events.push((after, -1));
+        }
+    }
+    events.sort_unstable();
+
+    let mut segments = Vec::new();
+    let mut depth = 0;
+    let mut events = events.into_iter().peekable();
+    while let Some((pos, delta)) = events.next() {
+        depth += delta;
+        while let Some((_, delta)) = events.next_if(|(next, _)| *next == pos) {
+            depth += delta;
+        }
+        let end = match events.peek() {
+            Some((next, _)) => next - 1,
+            None if depth > 0 => i64::MAX,
+            None => break,
+        };
+        segments.push((pos..=end, depth as usize));
+    }
+
+    segments
+}
+
+/// Integers within `within` covered by exactly `k` of the given ranges
+pub fn covered_exactly(
+    ranges: &[RangeInclusive<i64>],
+    k: usize,
+    within: RangeInclusive<i64>,
+) -> IntervalSet {
+    let within = IntervalSet::from_ranges([within]);
+    if k == 0 {
+        return within.difference(&IntervalSet::from_ranges(ranges.iter().cloned()));
+    }
+
+    IntervalSet::from_ranges(
+        coverage(ranges)
+            .into_iter()
+            .filter(|(_, depth)| *depth == k)
+            .map(|(range, _)| range),
+    )
+    .intersection(&within)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let set = IntervalSet::from_ranges([5..=7, 1..=2, 3..=3, 10..=12, 11..=15]);
+        assert_eq!(set.ranges(), &[1..=3, 5..=7, 10..=15]);
+        assert_eq!(set.len(), 12);
+        assert!(set.contains(3) && set.contains(15) && !set.contains(4) && !set.contains(8));
+    }
+
+    #[test]
+    fn test_set_ops() {
+        let a = IntervalSet::from_ranges([1..=10, 20..=30]);
+        let b = IntervalSet::from_ranges([5..=25]);
+        assert_eq!(a.union(&b).ranges(), &[1..=30]);
+        assert_eq!(a.intersection(&b).ranges(), &[5..=10, 20..=25]);
+        assert_eq!(a.difference(&b).ranges(), &[1..=4, 26..=30]);
+        assert_eq!(b.difference(&a).ranges(), &[11..=19]);
+        assert!(IntervalSet::from_ranges([21..=22]).is_subset(&a));
+
+        let mut c = IntervalSet::default();
+        c.insert(3..=4);
+        c.insert(1..=1);
+        assert_eq!(c.to_string(), "1,3-4");
+    }
+
+    #[test]
+    fn test_coverage() {
+        let ranges = [1..=4, 3..=6, 4..=4, 9..=9];
+        assert_eq!(
+            coverage(&ranges),
+            vec![
+                (1..=2, 1),
+                (3..=3, 2),
+                (4..=4, 3),
+                (5..=6, 1),
+                (7..=8, 0),
+                (9..=9, 1)
+            ]
+        );
+        assert_eq!(covered_exactly(&ranges, 0, 0..=10).to_string(), "0,7-8,10");
+        assert_eq!(covered_exactly(&ranges, 1, 0..=10).to_string(), "1-2,5-6,9");
+        assert_eq!(covered_exactly(&ranges, 3, 0..=10).to_string(), "4");
+    }
+}
@@ -1,15 +1,27 @@
+mod interval;
+
+use std::ops::RangeInclusive;
+
+use interval::IntervalSet;
+
+fn parse_line(line: &str) -> (RangeInclusive<i64>, RangeInclusive<i64>) {
+    let (left, right) = line.split_once(',').unwrap();
+    let (left_left, left_right) = left.split_once('-').unwrap();
+    let (right_left, right_right) = right.split_once('-').unwrap();
+    let left = left_left.parse::<i64>().unwrap()..=left_right.parse::<i64>().unwrap();
+    let right = right_left.parse::<i64>().unwrap()..=right_right.parse::<i64>().unwrap();
+
+    (left, right)
+}
+
 fn solve1(input: &[&str]) -> u32 {
     let mut overlaps = 0;
     for line in input {
-        let (left, right) = line.split_once(',').unwrap();
-        let (left_left, left_right) = left.split_once('-').unwrap();
-        let (right_left, right_right) = right.split_once('-').unwrap();
-        let left = left_left.parse::<u32>().unwrap()..=left_right.parse::<u32>().unwrap();
-        let right = right_left.parse::<u32>().unwrap()..=right_right.parse::<u32>().unwrap();
-
-        if (left.contains(right.start()) && left.contains(right.end()))
-            || (right.contains(left.start()) && right.contains(left.end()))
-        {
+        let (left, right) = parse_line(line);
+        let left = IntervalSet::from_ranges([left]);
+        let right = IntervalSet::from_ranges([right]);
+
+        if left.is_subset(&right) || right.is_subset(&left) {
             overlaps += 1;
         }
     }
@@ -20,17 +32,11 @@ fn solve1(input: &[&str]) -> u32 {
 fn solve2(input: &[&str]) -> u32 {
     let mut overlaps = 0;
     for line in input {
-        let (left, right) = line.split_once(',').unwrap();
-        let (left_left, left_right) = left.split_once('-').unwrap();
-        let (right_left, right_right) = right.split_once('-').unwrap();
-        let left = left_left.parse::<u32>().unwrap()..=left_right.parse::<u32>().unwrap();
-        let right = right_left.parse::<u32>().unwrap()..=right_right.parse::<u32>().unwrap();
-
-        if left.contains(right.start())
-            || left.contains(right.end())
-            || right.contains(left.start())
-            || right.contains(left.end())
-        {
+        let (left, right) = parse_line(line);
+        let left = IntervalSet::from_ranges([left]);
+        let right = IntervalSet::from_ranges([right]);
+
+        if !left.intersection(&right).is_empty() {
             overlaps += 1;
         }
     }
@@ -38,11 +44,130 @@ fn solve2(input: &[&str]) -> u32 {
     overlaps
 }
 
+/// Sections between 1 and the highest assigned section that exactly `elves` elves are assigned to
+fn sections_covered_by(input: &[&str], elves: usize) -> IntervalSet {
+    let assignments: Vec<RangeInclusive<i64>> = input
+        .iter()
+        .flat_map(|line| {
+            let (left, right) = parse_line(line);
+            [left, right]
+        })
+        .collect();
+    let last_section = assignments.iter().map(|r| *r.end()).max().unwrap_or(0);
+
+    interval::covered_exactly(&assignments, elves, 1..=last_section)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Conflict {
+    line: usize,
+    left: RangeInclusive<i64>,
+    right: RangeInclusive<i64>,
+    overlap: RangeInclusive<i64>,
+    fully_contained: bool,
+}
+
+impl Conflict {
+    fn size(&self) -> u64 {
+        IntervalSet::from_ranges([self.overlap.clone()]).len()
+    }
+}
+
+/// Every pair whose assignments overlap, with 1-based line numbers
+fn conflict_report(input: &[&str]) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    for (i, line) in input.iter().enumerate() {
+        let (left, right) = parse_line(line);
+        let left_set = IntervalSet::from_ranges([left.clone()]);
+        let right_set = IntervalSet::from_ranges([right.clone()]);
+
+        if let Some(overlap) = left_set.intersection(&right_set).ranges().first() {
+            conflicts.push(Conflict {
+                line: i + 1,
+                overlap: overlap.clone(),
+                fully_contained: left_set.is_subset(&right_set) || right_set.is_subset(&left_set),
+                left,
+                right,
+            });
+        }
+    }
+
+    conflicts
+}
+
+fn render_report(conflicts: &[Conflict], json: bool) -> String {
+    let mut out = String::new();
+    if json {
+        out.push('[');
+        for (i, conflict) in conflicts.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str(&format!(
+                "{{\"line\":{},\"left\":[{},{}],\"right\":[{},{}],\"overlap\":[{},{}],\"size\":{},\"fully_contained\":{}}}",
+                conflict.line,
+                conflict.left.start(),
+                conflict.left.end(),
+                conflict.right.start(),
+                conflict.right.end(),
+                conflict.overlap.start(),
+                conflict.overlap.end(),
+                conflict.size(),
+                conflict.fully_contained
+            ));
+        }
+        out.push(']');
+    } else {
+        for conflict in conflicts {
+            out.push_str(&format!(
+                "line {}: {}-{},{}-{} overlap {}-{} ({} section{}){}\n",
+                conflict.line,
+                conflict.left.start(),
+                conflict.left.end(),
+                conflict.right.start(),
+                conflict.right.end(),
+                conflict.overlap.start(),
+                conflict.overlap.end(),
+                conflict.size(),
+                if conflict.size() == 1 { "" } else { "s" },
+                if conflict.fully_contained {
+                    ", fully contained"
+                } else {
+                    ""
+                }
+            ));
+        }
+    }
+
+    out
+}
+
 fn main() {
     let input: Vec<&str> = include_str!("input.txt").lines().collect();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--report") {
+        let json = args.iter().any(|a| a == "--json");
+        println!(
+            "{}",
+            render_report(&conflict_report(&input), json).trim_end()
+        );
+        return;
+    }
+
     println!("part 1: {}", solve1(&input));
     println!("part 2: {}", solve2(&input));
+
+    // `--coverage` lists the sections nobody or only one elf is assigned to
+    if args.iter().any(|a| a == "--coverage") {
+        for elves in [0, 1] {
+            let sections = sections_covered_by(&input, elves);
+            println!(
+                "sections with {elves} elves: {} [{sections}]",
+                sections.len()
+            );
+        }
+    }
 }
 
 #[cfg(test)]
@@ -62,4 +187,46 @@ mod tests {
     fn test2() {
         assert_eq!(solve2(INPUT), 4)
     }
+
+    #[test]
+    fn test_report() {
+        let conflicts = conflict_report(INPUT);
+        assert_eq!(conflicts.len(), solve2(INPUT) as usize);
+        assert_eq!(
+            conflicts.iter().filter(|c| c.fully_contained).count(),
+            solve1(INPUT) as usize
+        );
+        assert_eq!(
+            conflicts[0],
+            Conflict {
+                line: 3,
+                left: 5..=7,
+                right: 7..=9,
+                overlap: 7..=7,
+                fully_contained: false
+            }
+        );
+        assert_eq!(conflicts[1].size(), 5);
+
+        let report = render_report(&conflicts[..2], false);
+        assert_eq!(
+            report,
+            "line 3: 5-7,7-9 overlap 7-7 (1 section)\n\
+             line 4: 2-8,3-7 overlap 3-7 (5 sections), fully contained\n"
+        );
+        let json = render_report(&conflicts[..1], true);
+        assert_eq!(
+            json,
+            r#"[{"line":3,"left":[5,7],"right":[7,9],"overlap":[7,7],"size":1,"fully_contained":false}]"#
+        );
+    }
+
+    #[test]
+    fn test_coverage() {
+        assert_eq!(sections_covered_by(INPUT, 0).to_string(), "1");
+        assert_eq!(sections_covered_by(INPUT, 1).to_string(), "9");
+        assert_eq!(sections_covered_by(INPUT, 4).to_string(), "2,8");
+        assert_eq!(sections_covered_by(INPUT, 7).to_string(), "4-5");
+        assert_eq!(sections_covered_by(INPUT, 9).to_string(), "");
+    }
 }
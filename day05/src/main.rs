@@ -1,11 +1,13 @@
 use std::collections::HashMap;
+use std::fmt::Display;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Supplies {
     stacks: Vec<Vec<char>>,
 }
 
 struct Move {
+    line: usize,
     quantity: usize,
     from: usize,
     to: usize,
@@ -15,10 +17,19 @@ impl Supplies {
     fn new(input: &[&str]) -> Self {
         let mut input = input.iter().rev();
         let cols = input.next().unwrap();
+
+        // stack labels may span several digits, so key each one by the column it starts in
         let mut col_idx = HashMap::new();
-        for (i, col) in cols.chars().enumerate() {
-            if col.is_numeric() {
-                col_idx.insert(i, (col.to_digit(10).unwrap() - 1) as usize);
+        let mut label_start = None;
+        for (i, chr) in cols.chars().chain(std::iter::once(' ')).enumerate() {
+            match (chr.is_ascii_digit(), label_start) {
+                (true, None) => label_start = Some(i),
+                (false, Some(start)) => {
+                    let label: usize = cols[start..i].parse().unwrap();
+                    col_idx.insert(start, label - 1);
+                    label_start = None;
+                }
+                _ => (),
             }
         }
         let mut stacks = vec![Vec::new(); col_idx.len()];
@@ -32,70 +43,288 @@ impl Supplies {
 
         Self { stacks }
     }
+
+    fn top_crates(&self) -> String {
+        String::from_iter(
+            self.stacks
+                .iter()
+                .filter(|s| !s.is_empty())
+                .map(|s| s[s.len() - 1]),
+        )
+    }
 }
 
-impl Move {
-    fn new(input: &str) -> Self {
-        let parts: Vec<usize> = input
-            .split_whitespace()
-            .map(|c| c.parse::<usize>())
-            .filter_map(|s| s.ok())
+impl Display for Supplies {
+    /// Renders the same drawing format that `Supplies::new` parses
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let height = self.stacks.iter().map(|s| s.len()).max().unwrap_or(0);
+        // every column is wide enough for its crate and for the longest label below it
+        let width = (self.stacks.len().to_string().len() + 1).max(3);
+        for level in (0..height).rev() {
+            let row: Vec<String> = self
+                .stacks
+                .iter()
+                .map(|s| match s.get(level) {
+                    Some(chr) => format!("{:<width$}", format!("[{chr}]")),
+                    None => " ".repeat(width),
+                })
+                .collect();
+            writeln!(f, "{}", row.join(" "))?;
+        }
+
+        let labels: Vec<String> = (1..=self.stacks.len())
+            .map(|label| format!(" {label:<0$}", width - 1))
             .collect();
+        write!(f, "{}", labels.join(" "))
+    }
+}
 
-        Self {
-            quantity: parts[0],
-            from: parts[1] - 1,
-            to: parts[2] - 1,
-        }
+impl Move {
+    /// Parse `move N from A to B`, where stacks are labelled from 1
+    fn new(line: usize, input: &str) -> Result<Self, CraneError> {
+        let invalid = || CraneError::InvalidMove {
+            line,
+            text: input.to_string(),
+        };
+        let words: Vec<&str> = input.split_whitespace().collect();
+        let ["move", quantity, "from", from, "to", to] = words.as_slice() else {
+            return Err(invalid());
+        };
+        let (Ok(quantity), Ok(from), Ok(to)) = (
+            quantity.parse::<usize>(),
+            from.parse::<usize>(),
+            to.parse::<usize>(),
+        ) else {
+            return Err(invalid());
+        };
+        let index = |stack: usize| {
+            stack
+                .checked_sub(1)
+                .ok_or(CraneError::NoSuchStack { line, stack })
+        };
+
+        Ok(Self {
+            line,
+            quantity,
+            from: index(from)?,
+            to: index(to)?,
+        })
     }
 }
 
-fn parse_input(input: &[&str]) -> (Supplies, Vec<Move>) {
+fn parse_input(input: &[&str]) -> Result<(Supplies, Vec<Move>), CraneError> {
     let mut parts = input.split(|line| line.is_empty());
     let supplies = parts.next().unwrap();
     let moves = parts.next().unwrap();
 
+    // moves start after the drawing and the blank separator line
+    let first_move_line = supplies.len() + 2;
     let supplies = Supplies::new(supplies);
-    let moves: Vec<Move> = moves.iter().map(|m| Move::new(m)).collect();
+    let moves = moves
+        .iter()
+        .enumerate()
+        .map(|(i, m)| Move::new(first_move_line + i, m))
+        .collect::<Result<Vec<Move>, CraneError>>()?;
 
-    (supplies, moves)
+    Ok((supplies, moves))
 }
 
-fn solve1(input: &[&str]) -> String {
-    let (mut supplies, moves) = parse_input(input);
-    for step in moves {
-        for _ in 0..step.quantity {
-            let moved_box = supplies.stacks[step.from].pop().unwrap();
-            supplies.stacks[step.to].push(moved_box);
+trait Crane {
+    /// Given the lifted crates from bottom to top, return them in the order they land
+    fn arrange(&self, lifted: Vec<char>) -> Vec<char>;
+}
+
+/// Moves crates one at a time
+struct CrateMover9000;
+
+/// Moves all crates of a step at once
+struct CrateMover9001;
+
+/// Moves at most `capacity` crates at once, keeping the order within each load
+struct BoundedCrane {
+    capacity: usize,
+}
+
+impl Crane for CrateMover9000 {
+    fn arrange(&self, mut lifted: Vec<char>) -> Vec<char> {
+        lifted.reverse();
+        lifted
+    }
+}
+
+impl Crane for CrateMover9001 {
+    fn arrange(&self, lifted: Vec<char>) -> Vec<char> {
+        lifted
+    }
+}
+
+impl Crane for BoundedCrane {
+    fn arrange(&self, lifted: Vec<char>) -> Vec<char> {
+        lifted
+            .rchunks(self.capacity.max(1))
+            .flatten()
+            .copied()
+            .collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum CraneError {
+    InvalidMove {
+        line: usize,
+        text: String,
+    },
+    NoSuchStack {
+        line: usize,
+        stack: usize,
+    },
+    NotEnoughCrates {
+        line: usize,
+        stack: usize,
+        needed: usize,
+        available: usize,
+    },
+}
+
+impl Display for CraneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CraneError::InvalidMove { line, text } => {
+                write!(f, "line {line}: expected \"move N from A to B\", found {text:?}")
+            }
+            CraneError::NoSuchStack { line, stack } => {
+                write!(f, "line {line}: stack {stack} does not exist")
+            }
+            CraneError::NotEnoughCrates {
+                line,
+                stack,
+                needed,
+                available,
+            } => write!(
+                f,
+                "line {line}: tried to take {needed} crates from stack {stack} which holds {available}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CraneError {}
+
+/// Returns the lifted crates, bottom to top, so the move can be undone
+fn apply_move(
+    supplies: &mut Supplies,
+    step: &Move,
+    crane: &dyn Crane,
+) -> Result<Vec<char>, CraneError> {
+    for stack in [step.from, step.to] {
+        if stack >= supplies.stacks.len() {
+            return Err(CraneError::NoSuchStack {
+                line: step.line,
+                stack: stack + 1,
+            });
         }
     }
 
-    String::from_iter(
-        supplies
-            .stacks
-            .iter()
-            .filter(|s| !s.is_empty())
-            .map(|s| s[s.len() - 1]),
-    )
+    let from = &mut supplies.stacks[step.from];
+    if from.len() < step.quantity {
+        return Err(CraneError::NotEnoughCrates {
+            line: step.line,
+            stack: step.from + 1,
+            needed: step.quantity,
+            available: from.len(),
+        });
+    }
+
+    let lifted: Vec<char> = from.drain(from.len() - step.quantity..).collect();
+    supplies.stacks[step.to].extend(crane.arrange(lifted.clone()));
+
+    Ok(lifted)
 }
 
-fn solve2(input: &[&str]) -> String {
-    let (mut supplies, moves) = parse_input(input);
+fn simulate(supplies: &mut Supplies, moves: &[Move], crane: &dyn Crane) -> Result<(), CraneError> {
     for step in moves {
-        let range = supplies.stacks[step.from].len() - step.quantity..;
-        let boxes: Vec<char> = supplies.stacks[step.from].drain(range).collect();
-        for mbox in boxes {
-            supplies.stacks[step.to].push(mbox);
+        apply_move(supplies, step, crane)?;
+    }
+
+    Ok(())
+}
+
+/// Steps a crane through a list of moves in either direction
+struct Replay<'a> {
+    supplies: Supplies,
+    moves: &'a [Move],
+    crane: &'a dyn Crane,
+    lifted: Vec<Vec<char>>,
+}
+
+impl<'a> Replay<'a> {
+    fn new(supplies: Supplies, moves: &'a [Move], crane: &'a dyn Crane) -> Self {
+        Self {
+            supplies,
+            moves,
+            crane,
+            lifted: Vec::new(),
         }
     }
 
-    String::from_iter(
-        supplies
-            .stacks
-            .iter()
-            .filter(|s| !s.is_empty())
-            .map(|s| s[s.len() - 1]),
-    )
+    /// Number of moves applied so far
+    fn position(&self) -> usize {
+        self.lifted.len()
+    }
+
+    fn supplies(&self) -> &Supplies {
+        &self.supplies
+    }
+
+    /// Apply the next move, returning false once every move has been applied
+    fn step_forward(&mut self) -> Result<bool, CraneError> {
+        let Some(step) = self.moves.get(self.position()) else {
+            return Ok(false);
+        };
+        let lifted = apply_move(&mut self.supplies, step, self.crane)?;
+        self.lifted.push(lifted);
+
+        Ok(true)
+    }
+
+    /// Undo the last applied move, returning false when already at the start
+    fn step_back(&mut self) -> bool {
+        let Some(lifted) = self.lifted.pop() else {
+            return false;
+        };
+        let step = &self.moves[self.position()];
+        let to = &mut self.supplies.stacks[step.to];
+        to.truncate(to.len() - lifted.len());
+        self.supplies.stacks[step.from].extend(lifted);
+
+        true
+    }
+
+    fn jump_to(&mut self, position: usize) -> Result<(), CraneError> {
+        while self.position() > position && self.step_back() {}
+        while self.position() < position && self.step_forward()? {}
+
+        Ok(())
+    }
+}
+
+fn solve_with(input: &[&str], crane: &dyn Crane) -> Result<String, CraneError> {
+    let (mut supplies, moves) = parse_input(input)?;
+    simulate(&mut supplies, &moves, crane)?;
+
+    Ok(supplies.top_crates())
+}
+
+fn solve1(input: &[&str]) -> String {
+    solve_with(input, &CrateMover9000).unwrap()
+}
+
+fn solve2(input: &[&str]) -> String {
+    solve_with(input, &CrateMover9001).unwrap()
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    std::env::args().skip_while(|a| a != name).nth(1)
 }
 
 fn main() {
@@ -103,6 +332,29 @@ fn main() {
 
     println!("part 1: {}", solve1(&input));
     println!("part 2: {}", solve2(&input));
+
+    if let Some(capacity) = arg_value("--capacity") {
+        let capacity = capacity.parse().unwrap();
+        match solve_with(&input, &BoundedCrane { capacity }) {
+            Ok(top) => println!("capacity {capacity}: {top}"),
+            Err(e) => println!("capacity {capacity}: {e}"),
+        }
+    }
+
+    if let Some(position) = arg_value("--replay") {
+        let (supplies, moves) = match parse_input(&input) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                println!("{e}");
+                return;
+            }
+        };
+        let mut replay = Replay::new(supplies, &moves, &CrateMover9000);
+        if let Err(e) = replay.jump_to(position.parse().unwrap()) {
+            println!("{e}");
+        }
+        println!("after move {}:\n{}", replay.position(), replay.supplies());
+    }
 }
 
 #[cfg(test)]
@@ -130,4 +382,115 @@ mod tests {
     fn test2() {
         assert_eq!(solve2(INPUT).as_str(), "MCD")
     }
+
+    #[test]
+    fn test_render() {
+        let (supplies, _) = parse_input(INPUT).unwrap();
+        assert_eq!(supplies.to_string(), INPUT[..4].join("\n"));
+    }
+
+    #[test]
+    fn test_many_stacks() {
+        for count in [12, 105] {
+            let supplies = Supplies {
+                stacks: (0..count)
+                    .map(|i| (0..i % 3).map(|h| (b'A' + h + i % 20) as char).collect())
+                    .collect(),
+            };
+            let rendered = supplies.to_string();
+            if count == 12 {
+                assert!(rendered.ends_with(" 9   10  11  12"));
+            } else {
+                assert!(rendered.ends_with(" 99   100  101  102  103  104  105"));
+            }
+
+            let lines: Vec<&str> = rendered.lines().collect();
+            let parsed = Supplies::new(&lines);
+            assert_eq!(parsed, supplies);
+            assert_eq!(parsed.to_string(), rendered);
+        }
+    }
+
+    #[test]
+    fn test_replay() {
+        let (supplies, moves) = parse_input(INPUT).unwrap();
+        let initial = supplies.clone();
+        let mut replay = Replay::new(supplies, &moves, &CrateMover9000);
+
+        assert!(replay.step_forward().unwrap());
+        assert_eq!(replay.supplies().top_crates(), "DCP");
+        replay.jump_to(4).unwrap();
+        assert_eq!(replay.supplies().top_crates(), "CMZ");
+        assert!(!replay.step_forward().unwrap());
+
+        replay.jump_to(2).unwrap();
+        assert_eq!(
+            replay.supplies().to_string(),
+            "        [Z]\n        [N]\n    [C] [D]\n    [M] [P]\n 1   2   3 "
+        );
+        while replay.step_back() {}
+        assert_eq!(replay.position(), 0);
+        assert_eq!(replay.supplies(), &initial);
+    }
+
+    #[test]
+    fn test_bounded_crane() {
+        assert_eq!(
+            solve_with(INPUT, &BoundedCrane { capacity: 1 }).unwrap(),
+            "CMZ"
+        );
+        assert_eq!(
+            solve_with(INPUT, &BoundedCrane { capacity: 3 }).unwrap(),
+            "MCD"
+        );
+        assert_eq!(
+            BoundedCrane { capacity: 2 }.arrange(vec!['A', 'B', 'C', 'D', 'E']),
+            vec!['D', 'E', 'B', 'C', 'A']
+        );
+    }
+
+    #[test]
+    fn test_invalid_moves() {
+        let mut input = INPUT.to_vec();
+        input.push("move 5 from 1 to 2");
+        assert_eq!(
+            solve_with(&input, &CrateMover9000),
+            Err(CraneError::NotEnoughCrates {
+                line: 10,
+                stack: 1,
+                needed: 5,
+                available: 1
+            })
+        );
+
+        input.pop();
+        input.push("move 1 from 4 to 2");
+        assert_eq!(
+            solve_with(&input, &CrateMover9001),
+            Err(CraneError::NoSuchStack { line: 10, stack: 4 })
+        );
+
+        input.pop();
+        input.push("move 1 from 0 to 2");
+        assert_eq!(
+            solve_with(&input, &CrateMover9001),
+            Err(CraneError::NoSuchStack { line: 10, stack: 0 })
+        );
+
+        for malformed in [
+            "move 1 from 2",
+            "move one from 2 to 1",
+            "take 1 from 2 to 1",
+        ] {
+            input.pop();
+            input.push(malformed);
+            assert_eq!(
+                solve_with(&input, &CrateMover9000),
+                Err(CraneError::InvalidMove {
+                    line: 10,
+                    text: malformed.to_string()
+                })
+            );
+        }
+    }
 }
@@ -1,152 +1,500 @@
-use std::{cell::RefCell, rc::Rc, rc::Weak};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Display;
 
-#[derive(Debug)]
-enum Record {
-    File(usize),
-    Directory(Rc<RefCell<Directory>>),
+type DirId = usize;
+
+/// Entries shown by one `ls`, with `None` as the size of a directory
+type Listing = BTreeSet<(String, Option<usize>)>;
+
+#[derive(Debug, PartialEq, Eq)]
+enum IssueKind {
+    CdAboveRoot,
+    CdIntoUnlisted(String),
+    RepeatedListing { first_line: usize },
+    ListingChanged { first_line: usize },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Issue {
+    line: usize,
+    kind: IssueKind,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            IssueKind::CdAboveRoot => write!(f, "`cd ..` while already at /"),
+            IssueKind::CdIntoUnlisted(name) => {
+                write!(
+                    f,
+                    "`cd {name}` into a directory its parent's `ls` didn't list"
+                )
+            }
+            IssueKind::RepeatedListing { first_line } => {
+                write!(f, "directory was already listed on line {first_line}")
+            }
+            IssueKind::ListingChanged { first_line } => write!(
+                f,
+                "listing differs from the one on line {first_line}, entries were merged"
+            ),
+        }
+    }
+}
+
+/// Compare a finished `ls` against any earlier listing of the same directory
+fn finish_listing(
+    listings: &mut HashMap<DirId, (usize, Listing)>,
+    issues: &mut Vec<Issue>,
+    dir: DirId,
+    (line, listing): (usize, Listing),
+) {
+    match listings.get(&dir) {
+        Some((first_line, first)) => issues.push(Issue {
+            line,
+            kind: if *first == listing {
+                IssueKind::RepeatedListing {
+                    first_line: *first_line,
+                }
+            } else {
+                IssueKind::ListingChanged {
+                    first_line: *first_line,
+                }
+            },
+        }),
+        None => {
+            listings.insert(dir, (line, listing));
+        }
+    }
 }
 
 #[derive(Debug)]
 struct Directory {
-    parent: Option<Weak<RefCell<Directory>>>,
-    contents: Vec<Record>,
-    tot_size: usize,
+    name: String,
+    parent: Option<DirId>,
+    dirs: BTreeMap<String, DirId>,
+    files: BTreeMap<String, usize>,
 }
 
-fn solve1(input: &[&str]) -> usize {
-    let root = Rc::new(RefCell::new(Directory {
-        parent: None,
-        contents: Vec::new(),
-        tot_size: 0,
-    }));
-
-    let mut total = 0;
-    #[allow(clippy::redundant_clone)]
-    let mut cur_dir = root.clone();
-    for line in input {
-        let mut parts = line.split_whitespace();
-        let part = parts.next().unwrap();
-        if part == "$" {
-            // command
-            if parts.next().unwrap() == "cd" {
-                if parts.next().unwrap() == ".." {
-                    let mut dir_tot = 0;
-                    for record in cur_dir.borrow().contents.iter() {
-                        match record {
-                            Record::File(size) => dir_tot += size,
-                            Record::Directory(dir) => dir_tot += dir.borrow().tot_size,
-                        }
-                    }
-                    cur_dir.borrow_mut().tot_size = dir_tot;
-                    if dir_tot <= 100000 {
-                        total += dir_tot;
-                    }
-                    if cur_dir.borrow().parent.is_some() {
-                        let parent = cur_dir.borrow().parent.as_ref().unwrap().upgrade().unwrap();
-                        cur_dir = parent;
+/// Filesystem reconstructed from a terminal transcript, with every directory stored in one arena
+#[derive(Debug)]
+struct FileSystem {
+    dirs: Vec<Directory>,
+}
+
+impl FileSystem {
+    const ROOT: DirId = 0;
+
+    fn parse(input: &[&str]) -> Self {
+        Self::parse_checked(input).0
+    }
+
+    /// Parse a transcript, collecting anything inconsistent along the way instead of rejecting it
+    fn parse_checked(input: &[&str]) -> (Self, Vec<Issue>) {
+        let mut fs = Self {
+            dirs: vec![Directory {
+                name: String::new(),
+                parent: None,
+                dirs: BTreeMap::new(),
+                files: BTreeMap::new(),
+            }],
+        };
+        let mut issues = Vec::new();
+
+        // the entries of each directory's first `ls`, and the one currently being read
+        let mut listings: HashMap<DirId, (usize, Listing)> = HashMap::new();
+        let mut cur_listing: Option<(usize, Listing)> = None;
+
+        let mut cur_dir = Self::ROOT;
+        for (i, line) in input.iter().enumerate() {
+            let line_no = i + 1;
+            let mut parts = line.split_whitespace();
+            let part = parts.next().unwrap();
+            if part == "$" {
+                if let Some(listing) = cur_listing.take() {
+                    finish_listing(&mut listings, &mut issues, cur_dir, listing);
+                }
+                match parts.next().unwrap() {
+                    "cd" => {
+                        cur_dir = match parts.next().unwrap() {
+                            "/" => Self::ROOT,
+                            ".." => fs.dirs[cur_dir].parent.unwrap_or_else(|| {
+                                issues.push(Issue {
+                                    line: line_no,
+                                    kind: IssueKind::CdAboveRoot,
+                                });
+                                Self::ROOT
+                            }),
+                            name => {
+                                let listed = listings.get(&cur_dir).map(|(_, l)| l);
+                                if listed.is_some_and(|l| !l.contains(&(name.to_string(), None))) {
+                                    issues.push(Issue {
+                                        line: line_no,
+                                        kind: IssueKind::CdIntoUnlisted(name.to_string()),
+                                    });
+                                }
+                                fs.subdir(cur_dir, name)
+                            }
+                        };
                     }
-                } else {
-                    let new_dir = Rc::new(RefCell::new(Directory {
-                        parent: Some(Rc::downgrade(&cur_dir)),
-                        contents: Vec::new(),
-                        tot_size: 0,
-                    }));
-                    cur_dir
-                        .borrow_mut()
-                        .contents
-                        .push(Record::Directory(new_dir.clone()));
-                    cur_dir = new_dir;
+                    _ => cur_listing = Some((line_no, Listing::new())),
                 }
+            } else if part == "dir" {
+                let name = parts.next().unwrap();
+                fs.subdir(cur_dir, name);
+                if let Some((_, listing)) = &mut cur_listing {
+                    listing.insert((name.to_string(), None));
+                }
+            } else {
+                let size = part.parse().unwrap();
+                let name = parts.next().unwrap();
+                fs.dirs[cur_dir].files.insert(name.to_string(), size);
+                if let Some((_, listing)) = &mut cur_listing {
+                    listing.insert((name.to_string(), Some(size)));
+                }
+            }
+        }
+        if let Some(listing) = cur_listing.take() {
+            finish_listing(&mut listings, &mut issues, cur_dir, listing);
+        }
+
+        (fs, issues)
+    }
+
+    /// Look up the named child of `parent`, creating it if it hasn't been seen yet
+    fn subdir(&mut self, parent: DirId, name: &str) -> DirId {
+        if let Some(id) = self.dirs[parent].dirs.get(name) {
+            return *id;
+        }
+
+        let id = self.dirs.len();
+        self.dirs.push(Directory {
+            name: name.to_string(),
+            parent: Some(parent),
+            dirs: BTreeMap::new(),
+            files: BTreeMap::new(),
+        });
+        self.dirs[parent].dirs.insert(name.to_string(), id);
+
+        id
+    }
+
+    fn path(&self, dir: DirId) -> String {
+        let mut names = Vec::new();
+        let mut cur = dir;
+        while let Some(parent) = self.dirs[cur].parent {
+            names.push(self.dirs[cur].name.as_str());
+            cur = parent;
+        }
+        names.reverse();
+
+        format!("/{}", names.join("/"))
+    }
+
+    /// Total size of every directory, indexed by `DirId`
+    fn dir_sizes(&self) -> Vec<usize> {
+        let mut sizes: Vec<usize> = self.dirs.iter().map(|d| d.files.values().sum()).collect();
+
+        // children are always created after their parent, so walking backwards sees them first
+        for id in (0..self.dirs.len()).rev() {
+            if let Some(parent) = self.dirs[id].parent {
+                sizes[parent] += sizes[id];
             }
-        } else if part != "dir" {
-            let size = part.parse().unwrap();
-            cur_dir.borrow_mut().contents.push(Record::File(size));
         }
+
+        sizes
     }
+}
 
-    total
+/// Directories at most `max_depth` below the root, children before their parent like `du -d`
+fn du(fs: &FileSystem, max_depth: Option<usize>) -> Vec<(String, usize)> {
+    fn visit(
+        fs: &FileSystem,
+        sizes: &[usize],
+        dir: DirId,
+        depth: usize,
+        max_depth: Option<usize>,
+        out: &mut Vec<(String, usize)>,
+    ) {
+        if max_depth.is_some_and(|max| depth > max) {
+            return;
+        }
+        for child in fs.dirs[dir].dirs.values() {
+            visit(fs, sizes, *child, depth + 1, max_depth, out);
+        }
+        out.push((fs.path(dir), sizes[dir]));
+    }
+
+    let mut out = Vec::new();
+    visit(
+        fs,
+        &fs.dir_sizes(),
+        FileSystem::ROOT,
+        0,
+        max_depth,
+        &mut out,
+    );
+
+    out
 }
 
-fn solve2(input: &[&str]) -> usize {
-    let total_disk_space = 70000000;
-    let needed_unused = 30000000;
-    let root = Rc::new(RefCell::new(Directory {
-        parent: None,
-        contents: Vec::new(),
-        tot_size: 0,
-    }));
-
-    let mut cur_dir = root.clone();
-    let mut dir_sizes = Vec::new();
-    for line in input {
-        let mut parts = line.split_whitespace();
-        let part = parts.next().unwrap();
-        if part == "$" {
-            // command
-            if parts.next().unwrap() == "cd" {
-                if parts.next().unwrap() == ".." {
-                    let mut dir_tot = 0;
-                    for record in cur_dir.borrow().contents.iter() {
-                        match record {
-                            Record::File(size) => dir_tot += size,
-                            Record::Directory(dir) => dir_tot += dir.borrow().tot_size,
-                        }
-                    }
-                    cur_dir.borrow_mut().tot_size = dir_tot;
-                    dir_sizes.push(dir_tot);
+/// Render the filesystem in the same format as the puzzle description
+fn tree(fs: &FileSystem) -> String {
+    fn visit(fs: &FileSystem, sizes: &[usize], dir: DirId, depth: usize, out: &mut String) {
+        let indent = "  ".repeat(depth);
+        let name = if dir == FileSystem::ROOT {
+            "/"
+        } else {
+            &fs.dirs[dir].name
+        };
+        out.push_str(&format!("{indent}- {name} (dir, size={})\n", sizes[dir]));
 
-                    if cur_dir.borrow().parent.is_some() {
-                        let parent = cur_dir.borrow().parent.as_ref().unwrap().upgrade().unwrap();
-                        cur_dir = parent;
-                    }
-                } else {
-                    let new_dir = Rc::new(RefCell::new(Directory {
-                        parent: Some(Rc::downgrade(&cur_dir)),
-                        contents: Vec::new(),
-                        tot_size: 0,
-                    }));
-                    cur_dir
-                        .borrow_mut()
-                        .contents
-                        .push(Record::Directory(new_dir.clone()));
-                    cur_dir = new_dir;
-                }
+        // dirs and files are interleaved by name like the puzzle's listing
+        let mut entries: Vec<(&String, Result<DirId, usize>)> = fs.dirs[dir]
+            .dirs
+            .iter()
+            .map(|(name, id)| (name, Ok(*id)))
+            .chain(
+                fs.dirs[dir]
+                    .files
+                    .iter()
+                    .map(|(name, size)| (name, Err(*size))),
+            )
+            .collect();
+        entries.sort_by_key(|(name, _)| *name);
+        for (name, entry) in entries {
+            match entry {
+                Ok(child) => visit(fs, sizes, child, depth + 1, out),
+                Err(size) => out.push_str(&format!("{indent}  - {name} (file, size={size})\n")),
             }
-        } else if part != "dir" {
-            let size = part.parse().unwrap();
-            cur_dir.borrow_mut().contents.push(Record::File(size));
         }
     }
 
-    loop {
-        let mut dir_tot = 0;
-        for record in cur_dir.borrow().contents.iter() {
-            match record {
-                Record::File(size) => dir_tot += size,
-                Record::Directory(dir) => dir_tot += dir.borrow().tot_size,
+    let mut out = String::new();
+    visit(fs, &fs.dir_sizes(), FileSystem::ROOT, 0, &mut out);
+
+    out
+}
+
+/// Files strictly larger than `min_size`, like `find -size +N`
+fn find_larger(fs: &FileSystem, min_size: usize) -> Vec<(String, usize)> {
+    let mut found = Vec::new();
+    for (id, dir) in fs.dirs.iter().enumerate() {
+        let dir_path = fs.path(id);
+        for (name, size) in &dir.files {
+            if *size > min_size {
+                found.push((format!("{}/{name}", dir_path.trim_end_matches('/')), *size));
             }
         }
-        cur_dir.borrow_mut().tot_size = dir_tot;
-        dir_sizes.push(dir_tot);
-        if cur_dir.borrow().parent.is_some() {
-            let parent = cur_dir.borrow().parent.as_ref().unwrap().upgrade().unwrap();
-            cur_dir = parent;
-        } else {
-            break;
+    }
+    found.sort();
+
+    found
+}
+
+fn largest_dirs(fs: &FileSystem, count: usize) -> Vec<(String, usize)> {
+    let sizes = fs.dir_sizes();
+    let mut ids: Vec<DirId> = (0..fs.dirs.len()).collect();
+    ids.sort_by_key(|id| std::cmp::Reverse(sizes[*id]));
+
+    ids.into_iter()
+        .take(count)
+        .map(|id| (fs.path(id), sizes[id]))
+        .collect()
+}
+
+fn solve1(input: &[&str]) -> usize {
+    let fs = FileSystem::parse(input);
+
+    fs.dir_sizes().into_iter().filter(|s| *s <= 100000).sum()
+}
+
+const TOTAL_DISK_SPACE: usize = 70000000;
+const NEEDED_UNUSED: usize = 30000000;
+
+/// Bytes that have to be deleted so that `required_unused` of `capacity` is free
+fn bytes_to_free(dir_sizes: &[usize], capacity: usize, required_unused: usize) -> usize {
+    let unused = capacity.saturating_sub(dir_sizes[FileSystem::ROOT]);
+
+    required_unused.saturating_sub(unused)
+}
+
+/// The smallest directory whose deletion leaves enough unused space for the update
+fn dir_to_delete(
+    fs: &FileSystem,
+    dir_sizes: &[usize],
+    capacity: usize,
+    required_unused: usize,
+) -> Option<DirId> {
+    let delete_size = bytes_to_free(dir_sizes, capacity, required_unused);
+    (0..fs.dirs.len())
+        .filter(|id| dir_sizes[*id] >= delete_size)
+        .min_by_key(|id| dir_sizes[*id])
+}
+
+fn solve2(input: &[&str]) -> usize {
+    let fs = FileSystem::parse(input);
+    let dir_sizes = fs.dir_sizes();
+
+    dir_sizes[dir_to_delete(&fs, &dir_sizes, TOTAL_DISK_SPACE, NEEDED_UNUSED).unwrap()]
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct CleanupPlan {
+    dirs: Vec<DirId>,
+    freed: usize,
+}
+
+/// Depth-first search over which directories to delete, in preorder so that deleting one skips
+/// the rest of its subtree
+struct CleanupSearch {
+    order: Vec<DirId>,
+    sizes: Vec<usize>,
+    /// Position just past each directory's subtree
+    subtree_end: Vec<usize>,
+    /// Most that can still be freed from each position on: deleting a directory frees at least as
+    /// much as any choice inside it, so this takes every outermost directory that's left
+    most_freeable: Vec<usize>,
+    to_free: usize,
+    chosen: Vec<DirId>,
+    best: CleanupPlan,
+}
+
+impl CleanupSearch {
+    fn branch(&mut self, pos: usize, freed: usize) {
+        if freed >= self.to_free {
+            if freed < self.best.freed {
+                self.best = CleanupPlan {
+                    dirs: self.chosen.clone(),
+                    freed,
+                };
+            }
+            return;
+        }
+        if pos == self.order.len() || freed + self.most_freeable[pos] < self.to_free {
+            return;
+        }
+
+        // delete this directory, unless that can't beat the best plan so far
+        let size = self.sizes[pos];
+        if freed + size < self.best.freed {
+            self.chosen.push(self.order[pos]);
+            self.branch(self.subtree_end[pos], freed + size);
+            self.chosen.pop();
+        }
+        if self.best.freed == self.to_free {
+            return;
+        }
+        // or keep it and look inside it and beyond
+        self.branch(pos + 1, freed);
+    }
+}
+
+/// Find the non-nested directories whose deletion frees enough space while deleting the fewest bytes
+fn plan_cleanup(fs: &FileSystem, capacity: usize, required_unused: usize) -> Option<CleanupPlan> {
+    let sizes = fs.dir_sizes();
+    let to_free = bytes_to_free(&sizes, capacity, required_unused);
+    // deleting the best single directory is always a valid plan, so the search only has to beat it
+    let single = dir_to_delete(fs, &sizes, capacity, required_unused)?;
+
+    // in preorder every subtree is a contiguous run, so deleting a directory skips to the end of it
+    let mut order = Vec::with_capacity(fs.dirs.len());
+    let mut subtree_end = vec![0; fs.dirs.len()];
+    let mut stack = vec![(FileSystem::ROOT, false)];
+    while let Some((dir, visited)) = stack.pop() {
+        if visited {
+            subtree_end[dir] = order.len();
+            continue;
         }
+        order.push(dir);
+        stack.push((dir, true));
+        stack.extend(
+            fs.dirs[dir]
+                .dirs
+                .values()
+                .rev()
+                .map(|child| (*child, false)),
+        );
     }
 
-    let delete_size = needed_unused - (total_disk_space - root.borrow().tot_size);
-    let mut possible_deletes: Vec<usize> =
-        dir_sizes.into_iter().filter(|s| *s > delete_size).collect();
-    possible_deletes.sort_unstable();
+    let subtree_end: Vec<usize> = order.iter().map(|dir| subtree_end[*dir]).collect();
+    let sizes: Vec<usize> = order.iter().map(|dir| sizes[*dir]).collect();
+    let mut most_freeable = vec![0; order.len() + 1];
+    for pos in (0..order.len()).rev() {
+        most_freeable[pos] = sizes[pos] + most_freeable[subtree_end[pos]];
+    }
 
-    possible_deletes[0]
+    let mut search = CleanupSearch {
+        best: CleanupPlan {
+            dirs: vec![single],
+            freed: sizes[order.iter().position(|dir| *dir == single).unwrap()],
+        },
+        order,
+        sizes,
+        subtree_end,
+        most_freeable,
+        to_free,
+        chosen: Vec::new(),
+    };
+    search.branch(0, 0);
+    search.best.dirs.sort_unstable();
+
+    Some(search.best)
+}
+
+fn print_listing(listing: &[(String, usize)]) {
+    for (path, size) in listing {
+        println!("{size}\t{path}");
+    }
+}
+
+fn print_plan(fs: &FileSystem, capacity: usize, required_unused: usize) {
+    let Some(plan) = plan_cleanup(fs, capacity, required_unused) else {
+        println!("not enough deletable space");
+        return;
+    };
+
+    let sizes = fs.dir_sizes();
+    let listing: Vec<(String, usize)> =
+        plan.dirs.iter().map(|d| (fs.path(*d), sizes[*d])).collect();
+    print_listing(&listing);
+    println!("{}\ttotal", plan.freed);
 }
 
 fn main() {
     let input: Vec<&str> = include_str!("input.txt").lines().collect();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    if !args.is_empty() {
+        let fs = FileSystem::parse(&input);
+        match args.as_slice() {
+            ["du"] => print_listing(&du(&fs, None)),
+            ["du", "-d", depth] => print_listing(&du(&fs, Some(depth.parse().unwrap()))),
+            ["tree"] => print!("{}", tree(&fs)),
+            ["find", "-size", size] => {
+                let size = size.strip_prefix('+').unwrap_or(size);
+                print_listing(&find_larger(&fs, size.parse().unwrap()));
+            }
+            ["largest", count] => print_listing(&largest_dirs(&fs, count.parse().unwrap())),
+            ["validate"] => {
+                let (_, issues) = FileSystem::parse_checked(&input);
+                for issue in &issues {
+                    println!("{issue}");
+                }
+                println!("{} issues", issues.len());
+            }
+            ["plan"] => print_plan(&fs, TOTAL_DISK_SPACE, NEEDED_UNUSED),
+            ["plan", capacity, required] => {
+                print_plan(&fs, capacity.parse().unwrap(), required.parse().unwrap())
+            }
+            _ => println!(
+                "usage: day07 [du [-d N] | tree | find -size +N | largest K | plan [CAPACITY REQUIRED] | validate]"
+            ),
+        }
+        return;
+    }
+
     println!("part 1: {}", solve1(&input));
     println!("part 2: {}", solve2(&input));
 }
@@ -190,4 +538,192 @@ mod tests {
     fn test2() {
         assert_eq!(solve2(INPUT), 24933642)
     }
+
+    #[test]
+    fn test_filesystem() {
+        let fs = FileSystem::parse(INPUT);
+        let sizes = fs.dir_sizes();
+        let by_path: Vec<(String, usize)> = (0..fs.dirs.len())
+            .map(|id| (fs.path(id), sizes[id]))
+            .collect();
+        assert_eq!(
+            by_path,
+            vec![
+                ("/".to_string(), 48381165),
+                ("/a".to_string(), 94853),
+                ("/d".to_string(), 24933642),
+                ("/a/e".to_string(), 584),
+            ]
+        );
+        assert_eq!(fs.dirs[1].files["h.lst"], 62596);
+    }
+
+    #[test]
+    fn test_queries() {
+        let fs = FileSystem::parse(INPUT);
+        assert_eq!(
+            du(&fs, Some(1)),
+            vec![
+                ("/a".to_string(), 94853),
+                ("/d".to_string(), 24933642),
+                ("/".to_string(), 48381165)
+            ]
+        );
+        assert_eq!(du(&fs, None)[0], ("/a/e".to_string(), 584));
+        assert_eq!(
+            find_larger(&fs, 8033020),
+            vec![
+                ("/b.txt".to_string(), 14848514),
+                ("/c.dat".to_string(), 8504156)
+            ]
+        );
+        assert_eq!(
+            largest_dirs(&fs, 2),
+            vec![("/".to_string(), 48381165), ("/d".to_string(), 24933642)]
+        );
+        assert_eq!(
+            tree(&fs),
+            "- / (dir, size=48381165)
+  - a (dir, size=94853)
+    - e (dir, size=584)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir, size=24933642)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)
+"
+        );
+    }
+
+    #[test]
+    fn test_cleanup_plan() {
+        let fs = FileSystem::parse(INPUT);
+        assert_eq!(
+            plan_cleanup(&fs, TOTAL_DISK_SPACE, NEEDED_UNUSED),
+            Some(CleanupPlan {
+                dirs: vec![2],
+                freed: 24933642
+            })
+        );
+        // /a and /a/e are nested, so the best plan is /a alone rather than both
+        assert_eq!(
+            plan_cleanup(&fs, 48381165, 90000),
+            Some(CleanupPlan {
+                dirs: vec![1],
+                freed: 94853
+            })
+        );
+        assert_eq!(
+            plan_cleanup(&fs, 48381165, 0),
+            Some(CleanupPlan {
+                dirs: vec![],
+                freed: 0
+            })
+        );
+        assert_eq!(plan_cleanup(&fs, 48381165, 50000000), None);
+
+        // with two small sibling directories, deleting both beats one large one
+        let mut input = INPUT.to_vec();
+        input.extend([
+            "$ cd /", "$ cd d", "$ ls", "dir x", "dir y", "$ cd x", "$ ls", "600 p",
+        ]);
+        input.extend(["$ cd ..", "$ cd y", "$ ls", "500 q"]);
+        let fs = FileSystem::parse(&input);
+        let plan = plan_cleanup(&fs, fs.dir_sizes()[0], 1090).unwrap();
+        assert_eq!(plan.freed, 1100);
+        let paths: Vec<String> = plan.dirs.iter().map(|d| fs.path(*d)).collect();
+        assert_eq!(paths, vec!["/d/x", "/d/y"]);
+
+        // terabyte-sized directories cost no more to plan than small ones
+        let input = [
+            "$ cd /",
+            "$ ls",
+            "dir a",
+            "dir b",
+            "dir c",
+            "$ cd a",
+            "$ ls",
+            "3000000000000 x",
+            "$ cd ..",
+            "$ cd b",
+            "$ ls",
+            "2000000000001 y",
+            "$ cd ..",
+            "$ cd c",
+            "$ ls",
+            "1000000000000 z",
+        ];
+        let fs = FileSystem::parse(&input);
+        let plan = plan_cleanup(&fs, 6000000000001, 3000000000000).unwrap();
+        assert_eq!(plan.freed, 3000000000000);
+        assert_eq!(plan.dirs.len(), 1);
+        let plan = plan_cleanup(&fs, 6000000000001, 3000000000001).unwrap();
+        assert_eq!(plan.freed, 3000000000001);
+        assert_eq!(plan.dirs.len(), 2);
+    }
+
+    #[test]
+    fn test_validation() {
+        assert_eq!(FileSystem::parse_checked(INPUT).1, vec![]);
+
+        let mut input = INPUT.to_vec();
+        input.extend([
+            "$ cd ..",
+            "$ cd ..",
+            "$ cd /",
+            "$ ls",
+            "dir a",
+            "14848514 b.txt",
+            "8504156 c.dat",
+            "dir d",
+            "$ cd a",
+            "$ ls",
+            "dir e",
+            "29116 f",
+            "$ cd x",
+        ]);
+        let (fs, issues) = FileSystem::parse_checked(&input);
+        assert_eq!(
+            issues,
+            vec![
+                Issue {
+                    line: 25,
+                    kind: IssueKind::CdAboveRoot
+                },
+                Issue {
+                    line: 27,
+                    kind: IssueKind::RepeatedListing { first_line: 2 }
+                },
+                Issue {
+                    line: 33,
+                    kind: IssueKind::ListingChanged { first_line: 8 }
+                },
+                Issue {
+                    line: 36,
+                    kind: IssueKind::CdIntoUnlisted("x".to_string())
+                },
+            ]
+        );
+        assert_eq!(fs.dir_sizes()[FileSystem::ROOT], 48381165);
+        assert_eq!(
+            issues[2].to_string(),
+            "line 33: listing differs from the one on line 8, entries were merged"
+        );
+    }
+
+    #[test]
+    fn test_revisits() {
+        let mut input = INPUT.to_vec();
+        input.extend([
+            "$ cd /", "$ cd a", "$ ls", "dir e", "29116 f", "$ cd e", "$ ls", "584 i",
+        ]);
+        assert_eq!(solve1(&input), 95437);
+        assert_eq!(solve2(&input), 24933642);
+    }
 }
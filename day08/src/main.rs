@@ -1,147 +1,304 @@
-fn parse_input(input: &[&str]) -> Vec<Vec<u8>> {
-    let mut rows = Vec::new();
-    for line in input {
-        rows.push(
-            line.chars()
-                .map(|c| c.to_digit(10).unwrap() as u8)
-                .collect(),
-        );
+use std::fmt::Display;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HeightEncoding {
+    /// One digit per tree, `0` to `9`
+    Digits,
+    /// One lowercase letter per tree, `a` being the shortest
+    Letters,
+    /// Comma separated numbers of any size
+    Csv,
+}
+
+impl FromStr for HeightEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "digits" => Ok(Self::Digits),
+            "letters" => Ok(Self::Letters),
+            "csv" => Ok(Self::Csv),
+            _ => Err(format!("unknown height encoding {s:?}")),
+        }
     }
+}
 
-    rows
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    InvalidHeight {
+        line: usize,
+        column: usize,
+        value: String,
+    },
+    NotRectangular {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
 }
 
-fn solve1(input: &[&str]) -> usize {
-    let mut tot_visible = 0;
-    let input = parse_input(input);
-    for y in 1..input.len() - 1 {
-        for x in 1..input[0].len() - 1 {
-            let height = input[y][x];
-            let mut visible = true;
-
-            // test left
-            let mut i = x;
-            while i > 0 {
-                i -= 1;
-
-                if input[y][i] >= height {
-                    visible = false;
-                    break;
-                }
-            }
-            if visible {
-                tot_visible += 1;
-                continue;
-            }
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::InvalidHeight {
+                line,
+                column,
+                value,
+            } => write!(f, "line {line}, column {column}: invalid height {value:?}"),
+            ParseError::NotRectangular {
+                line,
+                expected,
+                found,
+            } => write!(f, "line {line}: expected {expected} trees, found {found}"),
+        }
+    }
+}
 
-            // test right
-            visible = true;
-            let mut i = x;
-            while i < input[0].len() - 1 {
-                i += 1;
-                if input[y][i] >= height {
-                    visible = false;
-                    break;
-                }
-            }
-            if visible {
-                tot_visible += 1;
-                continue;
-            }
+impl std::error::Error for ParseError {}
 
-            // test up
-            visible = true;
-            let mut i = y;
-            while i > 0 {
-                i -= 1;
-                if input[i][x] >= height {
-                    visible = false;
-                    break;
-                }
-            }
-            if visible {
-                tot_visible += 1;
-                continue;
+fn parse_grid(input: &[&str], encoding: HeightEncoding) -> Result<Vec<Vec<u32>>, ParseError> {
+    let mut rows: Vec<Vec<u32>> = Vec::new();
+    for (i, line) in input.iter().enumerate() {
+        let invalid = |column: usize, value: &dyn ToString| ParseError::InvalidHeight {
+            line: i + 1,
+            column: column + 1,
+            value: value.to_string(),
+        };
+        let row = match encoding {
+            HeightEncoding::Digits => line
+                .chars()
+                .enumerate()
+                .map(|(column, c)| c.to_digit(10).ok_or_else(|| invalid(column, &c)))
+                .collect::<Result<Vec<u32>, _>>()?,
+            HeightEncoding::Letters => line
+                .chars()
+                .enumerate()
+                .map(|(column, c)| match c {
+                    'a'..='z' => Ok(c as u32 - 'a' as u32),
+                    _ => Err(invalid(column, &c)),
+                })
+                .collect::<Result<Vec<u32>, _>>()?,
+            HeightEncoding::Csv => line
+                .split(',')
+                .map(str::trim)
+                .enumerate()
+                .map(|(column, cell)| cell.parse().map_err(|_| invalid(column, &cell)))
+                .collect::<Result<Vec<u32>, _>>()?,
+        };
+
+        if let Some(first) = rows.first() {
+            if first.len() != row.len() {
+                return Err(ParseError::NotRectangular {
+                    line: i + 1,
+                    expected: first.len(),
+                    found: row.len(),
+                });
             }
+        }
+        rows.push(row);
+    }
 
-            // test down
-            visible = true;
-            let mut i = y;
-            while i < input.len() - 1 {
-                i += 1;
+    Ok(rows)
+}
 
-                if input[i][x] >= height {
-                    visible = false;
-                    break;
-                }
-            }
-            if visible {
-                tot_visible += 1;
-                continue;
-            }
+fn parse_input(input: &[&str]) -> Vec<Vec<u32>> {
+    parse_grid(input, HeightEncoding::Digits).unwrap()
+}
+
+/// Walk a line of trees from its start, returning for each tree whether it is visible from that
+/// end and how many trees it can see looking back toward it
+fn scan_line(heights: impl Iterator<Item = u32>) -> Vec<(bool, u64)> {
+    let mut result = Vec::new();
+    let mut tallest = None;
+    // indices of trees not yet blocked by a taller one, heights strictly decreasing
+    let mut blockers: Vec<(usize, u32)> = Vec::new();
+    for (i, height) in heights.enumerate() {
+        let visible = tallest.is_none_or(|t| height > t);
+        tallest = tallest.max(Some(height));
+
+        while blockers.last().is_some_and(|(_, h)| *h < height) {
+            blockers.pop();
         }
+        let distance = match blockers.last() {
+            Some((j, _)) => i - j,
+            None => i,
+        };
+        blockers.push((i, height));
+
+        result.push((visible, distance as u64));
     }
 
-    tot_visible + input.len() * 2 + (input[0].len() - 2) * 2
-}
-
-fn solve2(input: &[&str]) -> u32 {
-    let mut best_score = 0;
-    let input = parse_input(input);
-    for (y, row) in input.iter().enumerate() {
-        for (x, col) in row.iter().enumerate() {
-            let mut left_score = 0;
-            if x > 0 {
-                for i in (0..x).rev() {
-                    left_score += 1;
-                    if input[y][i] >= *col {
-                        break;
-                    }
-                }
-            }
+    result
+}
 
-            let mut right_score = 0;
-            if x < row.len() - 1 {
-                for i in x + 1..=row.len() - 1 {
-                    right_score += 1;
-                    if input[y][i] >= *col {
-                        break;
-                    }
-                }
-            }
+/// Fold the scan of one line of trees, walked in `coords` order, into the visibility and scores
+fn sweep(
+    grid: &[Vec<u32>],
+    visible: &mut [Vec<bool>],
+    scores: &mut [Vec<u64>],
+    coords: impl Iterator<Item = (usize, usize)> + Clone,
+) {
+    let scanned = scan_line(coords.clone().map(|(x, y)| grid[y][x]));
+    for ((x, y), (seen, distance)) in coords.zip(scanned) {
+        visible[y][x] |= seen;
+        scores[y][x] *= distance;
+    }
+}
 
-            let mut up_score = 0;
-            if y > 0 {
-                for i in (0..y).rev() {
-                    up_score += 1;
-                    if input[i][x] >= *col {
-                        break;
-                    }
-                }
-            }
+/// Visibility from outside the forest and scenic score of every tree, each in linear time
+fn analyze(grid: &[Vec<u32>]) -> (Vec<Vec<bool>>, Vec<Vec<u64>>) {
+    let height = grid.len();
+    let width = grid.first().map_or(0, |row| row.len());
+    let mut visible = vec![vec![false; width]; height];
+    let mut scores = vec![vec![1; width]; height];
 
-            let mut down_score = 0;
-            if y < input.len() - 1 {
-                for row in input.iter().skip(y + 1) {
-                    down_score += 1;
-                    if row[x] >= *col {
-                        break;
-                    }
-                }
-            }
+    for y in 0..height {
+        sweep(grid, &mut visible, &mut scores, (0..width).map(|x| (x, y)));
+        sweep(
+            grid,
+            &mut visible,
+            &mut scores,
+            (0..width).rev().map(|x| (x, y)),
+        );
+    }
+    for x in 0..width {
+        sweep(grid, &mut visible, &mut scores, (0..height).map(|y| (x, y)));
+        sweep(
+            grid,
+            &mut visible,
+            &mut scores,
+            (0..height).rev().map(|y| (x, y)),
+        );
+    }
 
-            best_score = best_score.max(left_score * right_score * up_score * down_score);
-        }
+    (visible, scores)
+}
+
+/// The `count` highest scenic scores as `((x, y), score)`, ties broken by position
+fn top_spots(scores: &[Vec<u64>], count: usize) -> Vec<((usize, usize), u64)> {
+    let mut spots: Vec<((usize, usize), u64)> = scores
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, s)| ((x, y), *s)))
+        .collect();
+    spots.sort_by_key(|((x, y), score)| (std::cmp::Reverse(*score), *y, *x));
+    spots.truncate(count);
+
+    spots
+}
+
+fn to_csv<T: ToString>(grid: &[Vec<T>]) -> String {
+    let mut csv = String::new();
+    for row in grid {
+        let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
     }
 
-    best_score
+    csv
+}
+
+/// Scale every value to a grayscale pixel relative to the largest one
+fn to_pixels(grid: &[Vec<u64>]) -> Vec<u8> {
+    let max = grid.iter().flatten().max().copied().unwrap_or(0).max(1);
+
+    grid.iter()
+        .flatten()
+        .map(|v| (v * 255 / max) as u8)
+        .collect()
+}
+
+fn write_png(path: &Path, grid: &[Vec<u64>]) -> Result<(), Box<dyn std::error::Error>> {
+    let height = grid.len() as u32;
+    let width = grid.first().map_or(0, |row| row.len()) as u32;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&to_pixels(grid))?;
+
+    Ok(())
+}
+
+/// Write the score matrix and visibility mask to `dir` as both CSV and PNG
+fn export(
+    dir: &Path,
+    visible: &[Vec<bool>],
+    scores: &[Vec<u64>],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let visible: Vec<Vec<u64>> = visible
+        .iter()
+        .map(|row| row.iter().map(|v| u64::from(*v)).collect())
+        .collect();
+
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join("scores.csv"), to_csv(scores))?;
+    std::fs::write(dir.join("visible.csv"), to_csv(&visible))?;
+    write_png(&dir.join("scores.png"), scores)?;
+    write_png(&dir.join("visible.png"), &visible)?;
+
+    Ok(())
+}
+
+fn count_visible(visible: &[Vec<bool>]) -> usize {
+    visible.iter().flatten().filter(|v| **v).count()
+}
+
+fn best_score(scores: &[Vec<u64>]) -> u64 {
+    scores.iter().flatten().max().copied().unwrap_or(0)
+}
+
+#[cfg(test)]
+fn solve1(input: &[&str]) -> usize {
+    count_visible(&analyze(&parse_input(input)).0)
+}
+
+#[cfg(test)]
+fn solve2(input: &[&str]) -> u64 {
+    best_score(&analyze(&parse_input(input)).1)
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    std::env::args().skip_while(|a| a != name).nth(1)
 }
 
 fn main() {
     let input: Vec<&str> = include_str!("input.txt").lines().collect();
 
-    println!("part 1: {}", solve1(&input));
-    println!("part 2: {}", solve2(&input));
+    // another forest can be given with `--input FILE [--encoding digits|letters|csv]`
+    let grid = match arg_value("--input") {
+        Some(path) => {
+            let contents = std::fs::read_to_string(path).unwrap();
+            let lines: Vec<&str> = contents.lines().collect();
+            let encoding =
+                arg_value("--encoding").map_or(HeightEncoding::Digits, |e| e.parse().unwrap());
+            match parse_grid(&lines, encoding) {
+                Ok(grid) => grid,
+                Err(e) => {
+                    println!("{e}");
+                    return;
+                }
+            }
+        }
+        None => parse_input(&input),
+    };
+
+    let (visible, scores) = analyze(&grid);
+    println!("part 1: {}", count_visible(&visible));
+    println!("part 2: {}", best_score(&scores));
+
+    if let Some(dir) = arg_value("export") {
+        export(Path::new(&dir), &visible, &scores).unwrap();
+
+        let count = arg_value("--top").map_or(10, |n| n.parse().unwrap());
+        for ((x, y), score) in top_spots(&scores, count) {
+            println!("({x}, {y}): {score}");
+        }
+    }
 }
 
 #[cfg(test)]
@@ -159,4 +316,82 @@ mod tests {
     fn test2() {
         assert_eq!(solve2(INPUT), 8)
     }
+
+    #[test]
+    fn test_exports() {
+        let (visible, scores) = analyze(&parse_input(INPUT));
+        assert_eq!(top_spots(&scores, 2), vec![((2, 3), 8), ((1, 2), 6)]);
+        assert_eq!(to_csv(&scores).lines().nth(1), Some("0,1,4,1,0"));
+        assert_eq!(
+            to_csv(&visible).lines().nth(2),
+            Some("true,true,false,true,true")
+        );
+        assert_eq!(to_pixels(&scores)[5..10], [0, 31, 127, 31, 0]);
+
+        let dir = std::env::temp_dir().join("day08_test_exports");
+        export(&dir, &visible, &scores).unwrap();
+        let png = std::fs::read(dir.join("scores.png")).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_encodings() {
+        let letters = parse_grid(&["dan", "bcb"], HeightEncoding::Letters).unwrap();
+        assert_eq!(letters, vec![vec![3, 0, 13], vec![1, 2, 1]]);
+        let csv = parse_grid(&["10, 200,3", "4,5,60"], HeightEncoding::Csv).unwrap();
+        assert_eq!(csv, vec![vec![10, 200, 3], vec![4, 5, 60]]);
+
+        assert_eq!(
+            parse_grid(&["123", "1x3"], HeightEncoding::Digits),
+            Err(ParseError::InvalidHeight {
+                line: 2,
+                column: 2,
+                value: "x".to_string()
+            })
+        );
+        assert_eq!(
+            parse_grid(&["aB"], HeightEncoding::Letters),
+            Err(ParseError::InvalidHeight {
+                line: 1,
+                column: 2,
+                value: "B".to_string()
+            })
+        );
+        assert_eq!(
+            parse_grid(&["1,2", "3, x "], HeightEncoding::Csv),
+            Err(ParseError::InvalidHeight {
+                line: 2,
+                column: 2,
+                value: "x".to_string()
+            })
+        );
+        assert_eq!(
+            parse_grid(&["123", "12"], HeightEncoding::Digits),
+            Err(ParseError::NotRectangular {
+                line: 2,
+                expected: 3,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_degenerate_grids() {
+        assert_eq!(solve1(&["31415"]), 5);
+        assert_eq!(solve2(&["31415"]), 0);
+        assert_eq!(solve1(&["3", "1", "4"]), 3);
+        assert_eq!(solve1(&["7"]), 1);
+        assert_eq!(solve1(&[]), 0);
+        assert_eq!(solve2(&[]), 0);
+    }
+
+    #[test]
+    fn test_scan_line() {
+        let scanned = scan_line([2, 5, 5, 1, 2].into_iter());
+        assert_eq!(
+            scanned,
+            vec![(true, 0), (true, 1), (false, 1), (false, 1), (false, 2)]
+        );
+    }
 }
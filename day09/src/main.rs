@@ -1,4 +1,7 @@
 use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 struct Coords {
@@ -6,126 +9,215 @@ struct Coords {
     y: i32,
 }
 
-fn solve1(input: &[&str]) -> usize {
-    let mut head = Coords { x: 0, y: 0 };
-    let mut tail = Coords { x: 0, y: 0 };
-    let mut visited = HashSet::new();
+#[derive(Debug, Clone)]
+struct Rope {
+    knots: Vec<Coords>,
+    /// Whether the head accepts diagonal commands like `UR 3`
+    diagonal_moves: bool,
+}
 
-    let mut prev_head = head;
+impl Rope {
+    fn new(num_knots: usize) -> Self {
+        assert!(num_knots > 0, "a rope needs at least a head");
+        Self {
+            knots: vec![Coords { x: 0, y: 0 }; num_knots],
+            diagonal_moves: false,
+        }
+    }
 
-    for line in input {
+    fn with_diagonal_moves(mut self) -> Self {
+        self.diagonal_moves = true;
+        self
+    }
+
+    /// Parse a command like `R 4` into a unit step for the head and a repeat count
+    fn parse_move(&self, line: &str) -> (Coords, usize) {
         let (dir, num) = line.split_once(' ').unwrap();
-        for _ in 0..num.parse().unwrap() {
-            match dir {
-                "U" => head.y += 1,
-                "R" => head.x += 1,
-                "D" => head.y -= 1,
-                "L" => head.x -= 1,
-                _ => panic!("invalid direction {dir}"),
-            }
+        let step = match dir {
+            "U" => Coords { x: 0, y: 1 },
+            "R" => Coords { x: 1, y: 0 },
+            "D" => Coords { x: 0, y: -1 },
+            "L" => Coords { x: -1, y: 0 },
+            "UR" if self.diagonal_moves => Coords { x: 1, y: 1 },
+            "UL" if self.diagonal_moves => Coords { x: -1, y: 1 },
+            "DR" if self.diagonal_moves => Coords { x: 1, y: -1 },
+            "DL" if self.diagonal_moves => Coords { x: -1, y: -1 },
+            _ => panic!("invalid direction {dir}"),
+        };
 
-            let distance = (head.x - tail.x).abs() + (head.y - tail.y).abs();
-            // check if diagonal from tail, distance 2
-            if head.x != tail.x && head.y != tail.y {
-                if distance > 2 {
-                    tail = prev_head;
-                }
-            } else if distance == 2 {
-                tail = prev_head
-            }
+        (step, num.parse().unwrap())
+    }
+
+    /// Move the head one step and let every following knot catch up
+    fn step(&mut self, dir: Coords) {
+        self.knots[0].x += dir.x;
+        self.knots[0].y += dir.y;
 
-            visited.insert(tail);
-            prev_head = head;
+        for i in 1..self.knots.len() {
+            let head = self.knots[i - 1];
+            let knot = &mut self.knots[i];
+            let (dx, dy) = (head.x - knot.x, head.y - knot.y);
+            if dx.abs() <= 1 && dy.abs() <= 1 {
+                // still touching, so nothing further down the rope moves either
+                break;
+            }
+            knot.x += dx.signum();
+            knot.y += dy.signum();
         }
     }
+}
 
-    visited.len()
+/// Every position each knot occupied, starting with where it began
+#[derive(Debug)]
+struct Trajectories {
+    paths: Vec<Vec<Coords>>,
 }
 
-fn solve2(input: &[&str]) -> usize {
-    fn fix_sub_knots(knots: &mut [Coords], start_idx: usize) {
-        assert!(start_idx > 0);
-        if start_idx >= knots.len() {
-            return;
+impl Trajectories {
+    fn unique_visits(&self) -> Vec<usize> {
+        self.paths
+            .iter()
+            .map(|path| path.iter().collect::<HashSet<_>>().len())
+            .collect()
+    }
+
+    /// Cells visited by `knot` as rows from the top, along with the start cell
+    fn visit_grid(&self, knot: usize) -> (Vec<Vec<bool>>, (usize, usize)) {
+        let path = &self.paths[knot];
+        let min_x = path.iter().map(|c| c.x).min().unwrap();
+        let max_x = path.iter().map(|c| c.x).max().unwrap();
+        let min_y = path.iter().map(|c| c.y).min().unwrap();
+        let max_y = path.iter().map(|c| c.y).max().unwrap();
+
+        // y grows upward like the puzzle, so the top row is max_y
+        let to_cell = |c: &Coords| ((c.x - min_x) as usize, (max_y - c.y) as usize);
+        let mut grid =
+            vec![vec![false; (max_x - min_x + 1) as usize]; (max_y - min_y + 1) as usize];
+        for coords in path {
+            let (x, y) = to_cell(coords);
+            grid[y][x] = true;
         }
 
-        let head = knots[start_idx - 1];
-        let mut new_tail = knots[start_idx];
-
-        loop {
-            let distance = (head.x - new_tail.x).abs() + (head.y - new_tail.y).abs();
-
-            // check if diagonal from tail, distance 2
-            if head.x != new_tail.x && head.y != new_tail.y {
-                if distance <= 2 {
-                    break;
-                } else {
-                    // diagonal move
-                    if head.x > new_tail.x {
-                        new_tail.x += 1;
-                    } else {
-                        new_tail.x -= 1;
-                    }
-
-                    if head.y > new_tail.y {
-                        new_tail.y += 1;
-                    } else {
-                        new_tail.y -= 1;
-                    }
-                }
-            } else if distance <= 1 {
-                break;
-            } else {
-                // simple move
-                #[allow(clippy::comparison_chain)]
-                if head.x == new_tail.x {
-                    if head.y > new_tail.y {
-                        new_tail.y += 1;
-                    } else {
-                        new_tail.y -= 1;
-                    }
-                } else if head.x > new_tail.x {
-                    new_tail.x += 1;
-                } else {
-                    new_tail.x -= 1;
-                }
+        (grid, to_cell(&path[0]))
+    }
+
+    /// Draw the cells visited by `knot` the way the puzzle does, `s` marking the start
+    fn render(&self, knot: usize) -> String {
+        let (grid, start) = self.visit_grid(knot);
+        let mut out = String::new();
+        for (y, row) in grid.iter().enumerate() {
+            for (x, visited) in row.iter().enumerate() {
+                out.push(match ((x, y) == start, visited) {
+                    (true, _) => 's',
+                    (false, true) => '#',
+                    (false, false) => '.',
+                });
             }
+            out.push('\n');
+        }
 
-            knots[start_idx] = new_tail;
+        out
+    }
 
-            fix_sub_knots(knots, start_idx + 1);
+    /// One pixel per cell: visited cells white, the start grey and everything else black
+    fn write_png(&self, knot: usize, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let (grid, start) = self.visit_grid(knot);
+        let mut pixels = Vec::new();
+        for (y, row) in grid.iter().enumerate() {
+            for (x, visited) in row.iter().enumerate() {
+                pixels.push(match ((x, y) == start, visited) {
+                    (true, _) => 128,
+                    (false, true) => 255,
+                    (false, false) => 0,
+                });
+            }
         }
-    }
 
-    let num_knots = 10;
-    let mut knots = vec![Coords { x: 0, y: 0 }; num_knots];
-    let mut visited = HashSet::new();
+        let (width, height) = (grid[0].len() as u32, grid.len() as u32);
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&pixels)?;
+
+        Ok(())
+    }
+}
 
+/// Run every command, recording where each knot is after every step of the head
+fn record(input: &[&str], mut rope: Rope) -> Trajectories {
+    let mut paths: Vec<Vec<Coords>> = rope.knots.iter().map(|k| vec![*k]).collect();
     for line in input {
-        let (dir, num) = line.split_once(' ').unwrap();
-        for _ in 0..num.parse().unwrap() {
-            match dir {
-                "U" => knots[0].y += 1,
-                "R" => knots[0].x += 1,
-                "D" => knots[0].y -= 1,
-                "L" => knots[0].x -= 1,
-                _ => panic!("invalid direction {dir}"),
+        let (dir, count) = rope.parse_move(line);
+        for _ in 0..count {
+            rope.step(dir);
+            for (path, knot) in paths.iter_mut().zip(&rope.knots) {
+                path.push(*knot);
             }
+        }
+    }
 
-            fix_sub_knots(&mut knots, 1);
+    Trajectories { paths }
+}
 
-            visited.insert(knots[num_knots - 1]);
+/// Run every command and count the positions the tail visited
+fn simulate(input: &[&str], mut rope: Rope) -> usize {
+    let mut visited = HashSet::from([*rope.knots.last().unwrap()]);
+    for line in input {
+        let (dir, count) = rope.parse_move(line);
+        for _ in 0..count {
+            rope.step(dir);
+            visited.insert(*rope.knots.last().unwrap());
         }
     }
 
     visited.len()
 }
 
+fn solve1(input: &[&str]) -> usize {
+    simulate(input, Rope::new(2))
+}
+
+fn solve2(input: &[&str]) -> usize {
+    simulate(input, Rope::new(10))
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    std::env::args().skip_while(|a| a != name).nth(1)
+}
+
 fn main() {
     let input: Vec<&str> = include_str!("input.txt").lines().collect();
 
     println!("part 1: {}", solve1(&input));
     println!("part 2: {}", solve2(&input));
+
+    // try other ropes with `--knots N [--diagonal --input FILE]`, adding `--trace` prints how
+    // many cells every knot visited and draws the tail's, `--png FILE` saving that drawing instead
+    if let Some(knots) = arg_value("--knots") {
+        let mut rope = Rope::new(knots.parse().unwrap());
+        if std::env::args().any(|a| a == "--diagonal") {
+            rope = rope.with_diagonal_moves();
+        }
+        let file = arg_value("--input").map(|path| std::fs::read_to_string(path).unwrap());
+        let lines: Vec<&str> = match &file {
+            Some(contents) => contents.lines().collect(),
+            None => input,
+        };
+
+        if !std::env::args().any(|a| a == "--trace") {
+            println!("{knots} knots: {}", simulate(&lines, rope));
+            return;
+        }
+        let trajectories = record(&lines, rope);
+        for (knot, visits) in trajectories.unique_visits().iter().enumerate() {
+            println!("knot {knot}: {visits}");
+        }
+        let tail = trajectories.paths.len() - 1;
+        match arg_value("--png") {
+            Some(path) => trajectories.write_png(tail, Path::new(&path)).unwrap(),
+            None => print!("{}", trajectories.render(tail)),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -145,4 +237,53 @@ mod tests {
         let input = &["R 5", "U 8", "L 8", "D 3", "R 17", "D 10", "L 25", "U 20"];
         assert_eq!(solve2(input), 36)
     }
+
+    #[test]
+    fn test_rope_lengths() {
+        assert_eq!(simulate(INPUT, Rope::new(1)), 21);
+        assert_eq!(simulate(INPUT, Rope::new(10)), 1);
+    }
+
+    #[test]
+    fn test_diagonal_moves() {
+        let input = &["UR 3", "L 2", "DL 1"];
+        // with two knots the tail trails the head diagonally
+        assert_eq!(simulate(input, Rope::new(2).with_diagonal_moves()), 4);
+
+        let mut rope = Rope::new(3).with_diagonal_moves();
+        let (dir, count) = rope.parse_move("DR 4");
+        for _ in 0..count {
+            rope.step(dir);
+        }
+        assert_eq!(
+            rope.knots,
+            vec![
+                Coords { x: 4, y: -4 },
+                Coords { x: 3, y: -3 },
+                Coords { x: 2, y: -2 }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trajectories() {
+        let trajectories = record(INPUT, Rope::new(2));
+        assert_eq!(trajectories.unique_visits(), vec![21, 13]);
+        assert_eq!(trajectories.paths[0].len(), 25);
+        assert_eq!(
+            trajectories.render(1),
+            "..##.\n...##\n.####\n....#\ns###.\n"
+        );
+
+        let dir = std::env::temp_dir().join("day09_test_trajectories.png");
+        trajectories.write_png(1, &dir).unwrap();
+        assert_eq!(&std::fs::read(&dir).unwrap()[1..4], b"PNG");
+        std::fs::remove_file(dir).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid direction UR")]
+    fn test_diagonal_needs_option() {
+        simulate(&["UR 1"], Rope::new(2));
+    }
 }
@@ -1,97 +1,640 @@
-fn solve1(input: &[&str]) -> i32 {
-    let mut cycle = 1;
-    let mut reg_x: i32 = 1;
+use std::collections::HashMap;
+use std::fmt::Display;
 
-    let mut instructions = input.iter();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Instruction {
+    Noop,
+    Addx(i32),
+    Addy(i32),
+    Mul(i32),
+    /// Relative jump, taken once the instruction completes
+    Jmp(i32),
+}
 
-    let checkpoints = &[20, 60, 100, 140, 180, 220];
-    let mut checkpoint_x = Vec::new();
-
-    let mut busy = 0;
-    let mut x_effect = 0;
-    while cycle <= 220 {
-        if busy == 0 {
-            reg_x += x_effect;
-            x_effect = 0;
-            let mut parts = instructions.next().unwrap().split_whitespace();
-            let opcode = parts.next().unwrap();
-            match opcode {
-                "noop" => busy = 1,
-                "addx" => {
-                    busy = 2;
-                    x_effect = parts.next().unwrap().parse().unwrap();
-                }
-                _ => panic!("invalid opcode: {opcode}"),
+/// Builds an instruction from its argument
+type Constructor = fn(i32) -> Instruction;
+
+impl Instruction {
+    /// Every mnemonic the CPU can run, with the constructor for those taking an argument. This is
+    /// the only list of instructions: `parse` and `InstructionSet` both go through it.
+    const OPCODES: &'static [(&'static str, Option<Constructor>)] = &[
+        ("noop", None),
+        ("addx", Some(Instruction::Addx)),
+        ("addy", Some(Instruction::Addy)),
+        ("mul", Some(Instruction::Mul)),
+        ("jmp", Some(Instruction::Jmp)),
+    ];
+
+    fn mnemonics() -> impl Iterator<Item = &'static str> {
+        Self::OPCODES.iter().map(|(mnemonic, _)| *mnemonic)
+    }
+
+    fn parse(mnemonic: &str, arg: Option<&str>) -> Option<Self> {
+        let (_, constructor) = Self::OPCODES.iter().find(|(m, _)| *m == mnemonic)?;
+        match (constructor, arg) {
+            (None, None) => Some(Instruction::Noop),
+            (Some(constructor), Some(arg)) => arg.parse().ok().map(constructor),
+            _ => None,
+        }
+    }
+}
+
+/// Why an instruction couldn't be added to an `InstructionSet`
+#[derive(Debug, PartialEq, Eq)]
+enum InstructionSetError {
+    UnknownInstruction(String),
+    NoCycles(String),
+}
+
+impl Display for InstructionSetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstructionSetError::UnknownInstruction(mnemonic) => {
+                write!(f, "unknown instruction {mnemonic:?}")
+            }
+            InstructionSetError::NoCycles(mnemonic) => {
+                write!(f, "{mnemonic} must take at least one cycle")
             }
         }
-        if checkpoints.contains(&cycle) {
-            checkpoint_x.push(reg_x * cycle);
+    }
+}
+
+impl std::error::Error for InstructionSetError {}
+
+/// Which instructions a program may use and how many cycles each of them takes
+#[derive(Debug, Clone)]
+struct InstructionSet {
+    cycles: HashMap<&'static str, usize>,
+}
+
+impl InstructionSet {
+    fn standard() -> Self {
+        Self {
+            cycles: HashMap::from([("noop", 1), ("addx", 2)]),
+        }
+    }
+
+    /// The standard set plus `addy`, `mul` and `jmp` used by variant puzzles
+    fn extended() -> Self {
+        let mut set = Self::standard();
+        set.cycles.extend([("addy", 2), ("mul", 3), ("jmp", 1)]);
+        set
+    }
+
+    /// Enable `mnemonic`, or change its cost if it already is. Only instructions the CPU knows
+    /// how to run can be enabled.
+    fn with_cycles(mut self, mnemonic: &str, cycles: usize) -> Result<Self, InstructionSetError> {
+        let known = Instruction::mnemonics()
+            .find(|m| *m == mnemonic)
+            .ok_or_else(|| InstructionSetError::UnknownInstruction(mnemonic.to_string()))?;
+        if cycles == 0 {
+            return Err(InstructionSetError::NoCycles(mnemonic.to_string()));
         }
+        self.cycles.insert(known, cycles);
+        Ok(self)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct ParseError {
+    line: usize,
+    text: String,
+}
 
-        cycle += 1;
-        busy -= 1;
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: invalid instruction {:?}", self.line, self.text)
     }
+}
+
+impl std::error::Error for ParseError {}
 
-    checkpoint_x.iter().sum()
+/// Parse each line into an instruction paired with its cycle cost
+fn parse_program(
+    input: &[&str],
+    instruction_set: &InstructionSet,
+) -> Result<Vec<(Instruction, usize)>, ParseError> {
+    input
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let mut parts = line.split_whitespace();
+            let mnemonic = parts.next().unwrap_or("");
+            let instruction = match (parts.next(), parts.next()) {
+                (arg, None) => Instruction::parse(mnemonic, arg),
+                _ => None,
+            };
+            instruction
+                .zip(instruction_set.cycles.get(mnemonic).copied())
+                .ok_or(ParseError {
+                    line: i + 1,
+                    text: line.to_string(),
+                })
+        })
+        .collect()
 }
 
-fn solve2(input: &[&str]) -> String {
-    let mut screen = vec![vec![false; 40]; 6];
-
-    let mut cycle = 0;
-    let mut reg_x: i32 = 1;
-
-    let mut instructions = input.iter();
-
-    let mut busy = 0;
-    let mut x_effect = 0;
-    while cycle < 240 {
-        if busy == 0 {
-            reg_x += x_effect;
-            x_effect = 0;
-            let mut parts = instructions.next().unwrap().split_whitespace();
-            let opcode = parts.next().unwrap();
-            match opcode {
-                "noop" => busy = 1,
-                "addx" => {
-                    busy = 2;
-                    x_effect = parts.next().unwrap().parse().unwrap();
+/// Runs a program one cycle at a time, yielding `(cycle, x)` with the value of X *during* each
+/// cycle and stopping once execution runs off either end of the program
+#[derive(Debug, Clone)]
+struct Cpu<'a> {
+    program: &'a [(Instruction, usize)],
+    /// Index of the instruction currently executing
+    pc: usize,
+    x: i32,
+    y: i32,
+    cycle: usize,
+    /// Cycles left before the current instruction completes
+    busy: usize,
+    /// Set when an instruction fails, which halts the program
+    fault: Option<Fault>,
+}
+
+/// A register overflowed while completing the instruction at `pc`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fault {
+    cycle: usize,
+    pc: usize,
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "cycle {}: instruction {} overflowed a register",
+            self.cycle, self.pc
+        )
+    }
+}
+
+impl std::error::Error for Fault {}
+
+impl<'a> Cpu<'a> {
+    fn new(program: &'a [(Instruction, usize)]) -> Self {
+        Self {
+            program,
+            pc: 0,
+            x: 1,
+            y: 0,
+            cycle: 0,
+            busy: 0,
+            fault: None,
+        }
+    }
+}
+
+impl Iterator for Cpu<'_> {
+    type Item = (usize, i32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.busy == 0 {
+            self.busy = self.program.get(self.pc)?.1;
+        }
+
+        self.cycle += 1;
+        let during = (self.cycle, self.x);
+
+        self.busy -= 1;
+        if self.busy == 0 {
+            let mut next_pc = self.pc as i64 + 1;
+            let completed = match self.program[self.pc].0 {
+                Instruction::Noop => Some(()),
+                Instruction::Addx(v) => self.x.checked_add(v).map(|x| self.x = x),
+                Instruction::Addy(v) => self.y.checked_add(v).map(|y| self.y = y),
+                Instruction::Mul(v) => self.x.checked_mul(v).map(|x| self.x = x),
+                Instruction::Jmp(offset) => {
+                    next_pc = self.pc as i64 + offset as i64;
+                    Some(())
                 }
-                _ => panic!("invalid opcode: {opcode}"),
+            };
+            if completed.is_none() {
+                self.fault = Some(Fault {
+                    cycle: self.cycle,
+                    pc: self.pc,
+                });
+                next_pc = -1;
             }
+            // jumping before the start halts just like running past the end
+            self.pc = usize::try_from(next_pc).unwrap_or(self.program.len());
+        }
+
+        Some(during)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Crt {
+    width: usize,
+    height: usize,
+    /// How many pixels the sprite covers, centered on X
+    sprite_width: usize,
+}
+
+impl Crt {
+    fn standard() -> Self {
+        Self {
+            width: 40,
+            height: 6,
+            sprite_width: 3,
         }
+    }
+
+    fn blank(&self) -> Vec<Vec<bool>> {
+        vec![vec![false; self.width]; self.height]
+    }
 
-        let cur_row = (cycle / 40) as usize;
-        let cur_col = (cycle % 40) as usize;
+    /// Light the pixel drawn during `cycle` if the sprite at `reg_x` covers it
+    fn draw(&self, screen: &mut [Vec<bool>], cycle: usize, reg_x: i32) {
+        // widened so a sprite near the edge of the register's range doesn't overflow
+        let reg_x = reg_x as i64;
+        let (left, right) = (
+            (self.sprite_width as i64 - 1) / 2,
+            self.sprite_width as i64 / 2,
+        );
+        let cur_row = (cycle - 1) / self.width;
+        let cur_col = (cycle - 1) % self.width;
 
-        if (reg_x - 1..=reg_x + 1).contains(&(cur_col as i32)) {
+        if cur_row < self.height && (reg_x - left..=reg_x + right).contains(&(cur_col as i64)) {
             screen[cur_row][cur_col] = true;
         }
+    }
+
+    fn show(screen: &[Vec<bool>]) -> String {
+        let mut output = String::new();
+        for row in screen {
+            for col in row {
+                if *col {
+                    output.push('#');
+                } else {
+                    output.push('.');
+                }
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+
+    /// Draw one pixel per cycle until the screen is full or the program ends
+    fn render(&self, cpu: Cpu) -> String {
+        let mut screen = self.blank();
+        for (cycle, reg_x) in cpu.take(self.width * self.height) {
+            self.draw(&mut screen, cycle, reg_x);
+        }
+
+        Self::show(&screen)
+    }
+}
+
+fn signal_strength(program: &[(Instruction, usize)]) -> i64 {
+    let checkpoints = &[20, 60, 100, 140, 180, 220];
+
+    Cpu::new(program)
+        .take(220)
+        .filter(|(cycle, _)| checkpoints.contains(cycle))
+        .map(|(cycle, x)| cycle as i64 * x as i64)
+        .sum()
+}
+
+fn solve1(input: &[&str]) -> i64 {
+    let program = parse_program(input, &InstructionSet::standard()).unwrap();
+
+    signal_strength(&program)
+}
+
+fn solve2(input: &[&str]) -> String {
+    let program = parse_program(input, &InstructionSet::standard()).unwrap();
+
+    Crt::standard().render(Cpu::new(&program))
+}
+
+/// The 4x6 capital letters the CRT draws, rows from top to bottom
+const GLYPHS: &[(char, [&str; 6])] = &[
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+enum OcrError {
+    /// The screen isn't six rows of equal width
+    BadShape,
+    /// Glyph number `index` (starting at 0) beginning at `column` matched no known letter
+    UnknownGlyph { index: usize, column: usize },
+}
+
+impl Display for OcrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OcrError::BadShape => write!(f, "screen must be 6 rows of equal width"),
+            OcrError::UnknownGlyph { index, column } => {
+                write!(f, "unrecognized glyph {index} at column {column}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+/// Read the letters off a rendered screen, each glyph 4 columns wide followed by a blank one
+fn decode(screen: &str) -> Result<String, OcrError> {
+    let rows: Vec<&str> = screen.lines().collect();
+    let width = rows.first().map_or(0, |r| r.len());
+    if rows.len() != 6 || rows.iter().any(|r| r.len() != width) {
+        return Err(OcrError::BadShape);
+    }
+
+    let mut letters = String::new();
+    for (index, column) in (0..width).step_by(5).enumerate() {
+        let glyph: Vec<&str> = rows
+            .iter()
+            .map(|r| r.get(column..column + 4).unwrap_or(""))
+            .collect();
+        let letter = GLYPHS
+            .iter()
+            .find(|(_, pattern)| pattern.as_slice() == glyph.as_slice())
+            .ok_or(OcrError::UnknownGlyph { index, column })?;
+        letters.push(letter.0);
+    }
+
+    Ok(letters)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Breakpoint {
+    /// Pause before this cycle runs
+    Cycle(usize),
+    /// Pause before the instruction at this index starts
+    Instruction(usize),
+}
+
+/// Why the debugger handed control back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stop {
+    Step,
+    Breakpoint(Breakpoint),
+    /// X changed at the end of the cycle that just ran
+    Watch {
+        old: i32,
+        new: i32,
+    },
+    Halted,
+}
+
+/// Runs a `Cpu` one cycle at a time, pausing on breakpoints and drawing the CRT as it goes
+struct Debugger<'a> {
+    cpu: Cpu<'a>,
+    crt: Crt,
+    screen: Vec<Vec<bool>>,
+    breakpoints: Vec<Breakpoint>,
+    watch_x: bool,
+    /// Set while paused on a breakpoint so resuming doesn't stop there again
+    on_breakpoint: bool,
+}
+
+impl<'a> Debugger<'a> {
+    fn new(program: &'a [(Instruction, usize)], crt: Crt) -> Self {
+        Self {
+            cpu: Cpu::new(program),
+            crt,
+            screen: crt.blank(),
+            breakpoints: Vec::new(),
+            watch_x: false,
+            on_breakpoint: false,
+        }
+    }
+
+    fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    fn remove_breakpoint(&mut self, breakpoint: Breakpoint) -> bool {
+        let before = self.breakpoints.len();
+        self.breakpoints.retain(|b| *b != breakpoint);
+        self.breakpoints.len() != before
+    }
+
+    /// The breakpoint matching the cycle about to run, if any
+    fn breakpoint_hit(&self) -> Option<Breakpoint> {
+        self.breakpoints.iter().copied().find(|b| match *b {
+            Breakpoint::Cycle(cycle) => cycle == self.cpu.cycle + 1,
+            Breakpoint::Instruction(index) => self.cpu.busy == 0 && index == self.cpu.pc,
+        })
+    }
+
+    /// Run a single cycle, drawing its pixel
+    fn step(&mut self) -> Stop {
+        let old = self.cpu.x;
+        let Some((cycle, reg_x)) = self.cpu.next() else {
+            return Stop::Halted;
+        };
+        self.on_breakpoint = false;
+        self.crt.draw(&mut self.screen, cycle, reg_x);
+
+        if self.watch_x && self.cpu.x != old {
+            Stop::Watch {
+                old,
+                new: self.cpu.x,
+            }
+        } else {
+            Stop::Step
+        }
+    }
+
+    /// Run until a breakpoint or the watch triggers or the program halts
+    fn resume(&mut self) -> Stop {
+        loop {
+            if let Some(breakpoint) = self.breakpoint_hit().filter(|_| !self.on_breakpoint) {
+                self.on_breakpoint = true;
+                return Stop::Breakpoint(breakpoint);
+            }
+            let stop = self.step();
+            if stop != Stop::Step {
+                return stop;
+            }
+        }
+    }
+
+    fn registers(&self) -> String {
+        format!(
+            "cycle {} pc {} x {} y {}",
+            self.cpu.cycle, self.cpu.pc, self.cpu.x, self.cpu.y
+        )
+    }
 
-        cycle += 1;
-        busy -= 1;
+    /// The CRT with every pixel drawn so far
+    fn screen(&self) -> String {
+        Crt::show(&self.screen)
     }
 
-    let mut output = String::new();
-    for row in screen {
-        for col in row {
-            if col {
-                output.push('#');
-            } else {
-                output.push('.');
+    /// Run one command line, returning what to print or None to quit
+    fn command(&mut self, line: &str) -> Option<String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let breakpoint = |kind: &str, value: &str| match (kind, value.parse()) {
+            ("cycle", Ok(n)) => Some(Breakpoint::Cycle(n)),
+            ("instr", Ok(n)) => Some(Breakpoint::Instruction(n)),
+            _ => None,
+        };
+        let describe = |stop: Stop, registers: String, fault: Option<Fault>| match stop {
+            Stop::Step => registers,
+            Stop::Breakpoint(b) => format!("hit {b:?}: {registers}"),
+            Stop::Watch { old, new } => format!("x changed {old} -> {new}: {registers}"),
+            Stop::Halted => match fault {
+                Some(fault) => format!("halted, {fault}: {registers}"),
+                None => format!("halted: {registers}"),
+            },
+        };
+
+        let output = match words.as_slice() {
+            ["break" | "b", kind, value] => match breakpoint(kind, value) {
+                Some(b) => {
+                    self.add_breakpoint(b);
+                    format!("added {b:?}")
+                }
+                None => format!("unknown breakpoint {line:?}"),
+            },
+            ["delete" | "d", kind, value] => match breakpoint(kind, value) {
+                Some(b) if self.remove_breakpoint(b) => format!("removed {b:?}"),
+                _ => format!("no breakpoint {line:?}"),
+            },
+            ["watch" | "w"] => {
+                self.watch_x = !self.watch_x;
+                format!("watching x: {}", self.watch_x)
             }
+            ["step" | "s", rest @ ..] => {
+                let count = rest.first().and_then(|n| n.parse().ok()).unwrap_or(1);
+                let mut stop = Stop::Step;
+                for _ in 0..count {
+                    stop = self.step();
+                    if stop != Stop::Step {
+                        break;
+                    }
+                }
+                describe(stop, self.registers(), self.cpu.fault)
+            }
+            ["continue" | "c"] => {
+                let stop = self.resume();
+                describe(stop, self.registers(), self.cpu.fault)
+            }
+            ["regs" | "r"] => self.registers(),
+            ["screen"] => self.screen().trim_end().to_string(),
+            ["quit" | "q"] => return None,
+            _ => "commands: break|delete cycle|instr N, watch, step [N], continue, regs, screen, quit"
+                .to_string(),
+        };
+
+        Some(output)
+    }
+}
+
+/// Read debugger commands from stdin until it closes or `quit`
+fn debug_session(mut debugger: Debugger) {
+    use std::io::Write;
+
+    let mut line = String::new();
+    loop {
+        print!("(cpu) ");
+        std::io::stdout().flush().unwrap();
+        line.clear();
+        if std::io::stdin().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        match debugger.command(&line) {
+            Some(output) => println!("{output}"),
+            None => break,
         }
-        output.push('\n');
     }
+}
 
-    output
+fn arg_value(name: &str) -> Option<String> {
+    std::env::args().skip_while(|a| a != name).nth(1)
 }
 
 fn main() {
     let input: Vec<&str> = include_str!("input.txt").lines().collect();
 
-    println!("part 1: {}", solve1(&input));
-    println!("part 2: \n{}", solve2(&input));
+    // variant programs run with `--input FILE [--extended] [--cycles MNEMONIC=N,...]
+    // [--crt WIDTHxHEIGHT] [--sprite N]`, and `--debug` steps through the program reading commands from stdin instead
+    let debug = std::env::args().any(|a| a == "--debug");
+    let Some(path) = arg_value("--input") else {
+        if debug {
+            let program = parse_program(&input, &InstructionSet::standard()).unwrap();
+            debug_session(Debugger::new(&program, Crt::standard()));
+            return;
+        }
+        println!("part 1: {}", solve1(&input));
+        let screen = solve2(&input);
+        match decode(&screen) {
+            Ok(letters) => println!("part 2: {letters}\n{screen}"),
+            Err(e) => println!("part 2: ({e})\n{screen}"),
+        }
+        return;
+    };
+
+    let contents = std::fs::read_to_string(path).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut instruction_set = if std::env::args().any(|a| a == "--extended") {
+        InstructionSet::extended()
+    } else {
+        InstructionSet::standard()
+    };
+    for cost in arg_value("--cycles").iter().flat_map(|c| c.split(',')) {
+        let (mnemonic, cycles) = cost.split_once('=').unwrap();
+        instruction_set = match instruction_set.with_cycles(mnemonic, cycles.parse().unwrap()) {
+            Ok(instruction_set) => instruction_set,
+            Err(e) => {
+                println!("{e}");
+                return;
+            }
+        };
+    }
+    let program = match parse_program(&lines, &instruction_set) {
+        Ok(program) => program,
+        Err(e) => {
+            println!("{e}");
+            return;
+        }
+    };
+
+    let mut crt = Crt::standard();
+    if let Some((width, height)) = arg_value("--crt")
+        .as_deref()
+        .and_then(|c| c.split_once('x'))
+    {
+        crt.width = width.parse().unwrap();
+        crt.height = height.parse().unwrap();
+    }
+    if let Some(sprite_width) = arg_value("--sprite") {
+        crt.sprite_width = sprite_width.parse().unwrap();
+    }
+
+    if debug {
+        debug_session(Debugger::new(&program, crt));
+        return;
+    }
+    println!("signal strength: {}", signal_strength(&program));
+    print!("{}", crt.render(Cpu::new(&program)));
+    let mut cpu = Cpu::new(&program);
+    cpu.by_ref().take(crt.width * crt.height).for_each(drop);
+    if let Some(fault) = cpu.fault {
+        println!("halted early, {fault}");
+    }
 }
 
 #[cfg(test)]
@@ -124,4 +667,186 @@ mod tests {
         // println!("{answer}");
         assert_eq!(solve2(&input), answer)
     }
+
+    #[test]
+    fn test_decode() {
+        let screen = [
+            "####.####.###..###..###..####.####.####.",
+            "#.......#.#..#.#..#.#..#.#.......#.#....",
+            "###....#..###..#..#.###..###....#..###..",
+            "#.....#...#..#.###..#..#.#.....#...#....",
+            "#....#....#..#.#....#..#.#....#....#....",
+            "#....####.###..#....###..#....####.#....",
+        ]
+        .join("\n");
+        assert_eq!(decode(&screen), Ok("FZBPBFZF".to_string()));
+
+        let input: Vec<&str> = INPUT.lines().collect();
+        assert_eq!(
+            decode(&solve2(&input)),
+            Err(OcrError::UnknownGlyph {
+                index: 0,
+                column: 0
+            })
+        );
+        assert_eq!(decode("#..#\n####"), Err(OcrError::BadShape));
+    }
+
+    #[test]
+    fn test_extended_instructions() {
+        let source = ["addx 2", "mul 3", "addy 5", "jmp 2", "addx 100", "noop"];
+        assert_eq!(
+            parse_program(&source, &InstructionSet::standard()),
+            Err(ParseError {
+                line: 2,
+                text: "mul 3".to_string()
+            })
+        );
+
+        let program = parse_program(&source, &InstructionSet::extended()).unwrap();
+        let mut cpu = Cpu::new(&program);
+        let xs: Vec<i32> = cpu.by_ref().map(|(_, x)| x).collect();
+        assert_eq!(xs, vec![1, 1, 3, 3, 3, 9, 9, 9, 9]);
+        assert_eq!(cpu.y, 5);
+
+        for mnemonic in Instruction::mnemonics() {
+            let parsed =
+                Instruction::parse(mnemonic, None).or(Instruction::parse(mnemonic, Some("1")));
+            assert!(parsed.is_some(), "{mnemonic} doesn't parse");
+        }
+
+        // costs can be changed per instruction
+        let cheap_mul = InstructionSet::extended().with_cycles("mul", 1).unwrap();
+        let program = parse_program(&source, &cheap_mul).unwrap();
+        assert_eq!(Cpu::new(&program).count(), 7);
+
+        // jumping backwards loops forever, so the CRT stops once the screen is full
+        let program = parse_program(&["addx 1", "jmp -1"], &InstructionSet::extended()).unwrap();
+        assert_eq!(Cpu::new(&program).take(1000).count(), 1000);
+
+        // overflowing a register halts the program instead of panicking
+        let program = parse_program(&["mul 2", "jmp -1"], &InstructionSet::extended()).unwrap();
+        let mut cpu = Cpu::new(&program);
+        assert_eq!(cpu.by_ref().count(), 123);
+        assert_eq!(cpu.fault, Some(Fault { cycle: 123, pc: 0 }));
+        assert_eq!(cpu.x, 1 << 30);
+        assert_eq!(
+            Crt::standard().render(Cpu::new(&program)).lines().count(),
+            6
+        );
+        let program = parse_program(&["addx 2147483647"], &InstructionSet::standard()).unwrap();
+        assert_eq!(
+            Crt::standard().render(Cpu::new(&program)),
+            Crt::standard().render(Cpu::new(&[(Instruction::Noop, 2)]))
+        );
+        let mut cpu = Cpu::new(&program);
+        cpu.by_ref().for_each(drop);
+        assert_eq!(cpu.fault, Some(Fault { cycle: 2, pc: 0 }));
+    }
+
+    #[test]
+    fn test_unknown_instruction_cost() {
+        assert_eq!(
+            InstructionSet::extended().with_cycles("foo", 1).err(),
+            Some(InstructionSetError::UnknownInstruction("foo".to_string()))
+        );
+        assert_eq!(
+            InstructionSet::extended().with_cycles("mul", 0).err(),
+            Some(InstructionSetError::NoCycles("mul".to_string()))
+        );
+        assert!(InstructionSet::standard().with_cycles("jmp", 1).is_ok());
+    }
+
+    #[test]
+    fn test_crt_geometry() {
+        let program =
+            parse_program(&["addx 2", "addx 2", "noop"], &InstructionSet::standard()).unwrap();
+        let crt = Crt {
+            width: 3,
+            height: 2,
+            sprite_width: 1,
+        };
+        assert_eq!(crt.render(Cpu::new(&program)), ".#.\n...\n");
+
+        let crt = Crt {
+            width: 6,
+            height: 1,
+            sprite_width: 4,
+        };
+        assert_eq!(crt.render(Cpu::new(&program)), "#####.\n");
+    }
+
+    #[test]
+    fn test_cpu() {
+        let program =
+            parse_program(&["noop", "addx 3", "addx -5"], &InstructionSet::standard()).unwrap();
+        let cycles: Vec<(usize, i32)> = Cpu::new(&program).collect();
+        assert_eq!(cycles, vec![(1, 1), (2, 1), (3, 1), (4, 4), (5, 4)]);
+
+        // a short program simply ends early instead of panicking
+        assert_eq!(solve1(&["addx 1", "noop"]), 0);
+
+        assert_eq!(
+            parse_program(&["noop", "addx"], &InstructionSet::standard()),
+            Err(ParseError {
+                line: 2,
+                text: "addx".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_debugger() {
+        let program = parse_program(
+            &["noop", "addx 3", "addx -5", "noop"],
+            &InstructionSet::standard(),
+        )
+        .unwrap();
+        let crt = Crt {
+            width: 3,
+            height: 2,
+            sprite_width: 3,
+        };
+        let mut debugger = Debugger::new(&program, crt);
+
+        assert_eq!(debugger.step(), Stop::Step);
+        assert_eq!(debugger.screen(), "#..\n...\n");
+
+        debugger.add_breakpoint(Breakpoint::Instruction(2));
+        debugger.add_breakpoint(Breakpoint::Cycle(6));
+        assert_eq!(
+            debugger.resume(),
+            Stop::Breakpoint(Breakpoint::Instruction(2))
+        );
+        assert_eq!(debugger.registers(), "cycle 3 pc 2 x 4 y 0");
+        assert_eq!(debugger.screen(), "###\n...\n");
+
+        debugger.watch_x = true;
+        assert_eq!(debugger.resume(), Stop::Watch { old: 4, new: -1 });
+        assert_eq!(debugger.resume(), Stop::Breakpoint(Breakpoint::Cycle(6)));
+        assert!(debugger.remove_breakpoint(Breakpoint::Cycle(6)));
+        assert!(!debugger.remove_breakpoint(Breakpoint::Cycle(6)));
+        assert_eq!(debugger.resume(), Stop::Halted);
+        assert_eq!(debugger.screen(), "###\n...\n");
+
+        let mut debugger = Debugger::new(&program, crt);
+        assert_eq!(debugger.command("break cycle 4").unwrap(), "added Cycle(4)");
+        assert_eq!(
+            debugger.command("c").unwrap(),
+            "hit Cycle(4): cycle 3 pc 2 x 4 y 0"
+        );
+        assert_eq!(
+            debugger.command("step 9").unwrap(),
+            "halted: cycle 6 pc 4 x -1 y 0"
+        );
+        assert_eq!(debugger.command("quit"), None);
+
+        // a watch interrupts a multi-cycle step too
+        let mut debugger = Debugger::new(&program, crt);
+        debugger.command("watch");
+        assert_eq!(
+            debugger.command("step 9").unwrap(),
+            "x changed 1 -> 4: cycle 3 pc 2 x 4 y 0"
+        );
+    }
 }
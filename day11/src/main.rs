@@ -1,18 +1,234 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::str::FromStr;
 
-#[derive(Debug, Clone)]
+/// A monkey's operation, computing the new worry level from the `old` one
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum Node {
     Const(usize),
     Add((Box<Node>, Box<Node>)),
+    Sub((Box<Node>, Box<Node>)),
     Mul((Box<Node>, Box<Node>)),
+    Div((Box<Node>, Box<Node>)),
+    Rem((Box<Node>, Box<Node>)),
     Old,
 }
 
-impl From<&str> for Node {
-    fn from(value: &str) -> Self {
-        match value {
-            "old" => Node::Old,
-            _ => Node::Const(value.parse().unwrap()),
+impl Node {
+    fn eval(&self, old: usize) -> Result<usize, EvalError> {
+        let (op, left, right) = match self {
+            Node::Const(val) => return Ok(*val),
+            Node::Old => return Ok(old),
+            Node::Add((left, right)) => ('+', left, right),
+            Node::Sub((left, right)) => ('-', left, right),
+            Node::Mul((left, right)) => ('*', left, right),
+            Node::Div((left, right)) => ('/', left, right),
+            Node::Rem((left, right)) => ('%', left, right),
+        };
+        let (left, right) = (left.eval(old)?, right.eval(old)?);
+
+        let result = match op {
+            '+' => left.checked_add(right),
+            '-' => left.checked_sub(right),
+            '*' => left.checked_mul(right),
+            '/' => left.checked_div(right),
+            _ => left.checked_rem(right),
+        };
+        result.ok_or(match op {
+            '-' => EvalError::Negative { left, right },
+            '/' | '%' => EvalError::DivideByZero,
+            _ => EvalError::Overflow { left, op, right },
+        })
+    }
+
+    /// Whether the result modulo m only depends on `old` modulo m, which is what lets part 2
+    /// keep worry levels small. Subtraction and division break that.
+    fn preserves_congruence(&self) -> bool {
+        match self {
+            Node::Const(_) | Node::Old => true,
+            Node::Add((left, right)) | Node::Mul((left, right)) => {
+                left.preserves_congruence() && right.preserves_congruence()
+            }
+            Node::Sub(_) | Node::Div(_) | Node::Rem(_) => false,
+        }
+    }
+}
+
+impl Display for Node {
+    /// Writes the expression back out, parenthesizing every nested operation
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (op, left, right) = match self {
+            Node::Const(val) => return write!(f, "{val}"),
+            Node::Old => return write!(f, "old"),
+            Node::Add((left, right)) => ('+', left, right),
+            Node::Sub((left, right)) => ('-', left, right),
+            Node::Mul((left, right)) => ('*', left, right),
+            Node::Div((left, right)) => ('/', left, right),
+            Node::Rem((left, right)) => ('%', left, right),
+        };
+        let wrap = |node: &Node| match node {
+            Node::Const(_) | Node::Old => node.to_string(),
+            _ => format!("({node})"),
+        };
+        write!(f, "{} {op} {}", wrap(left), wrap(right))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum EvalError {
+    Overflow { left: usize, op: char, right: usize },
+    Negative { left: usize, right: usize },
+    DivideByZero,
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalError::Overflow { left, op, right } => {
+                write!(f, "{left} {op} {right} overflows a worry level")
+            }
+            EvalError::Negative { left, right } => {
+                write!(f, "{left} - {right} gives a negative worry level")
+            }
+            EvalError::DivideByZero => write!(f, "division by zero"),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ExprError {
+    /// Something other than what the grammar allows at this character offset
+    Unexpected {
+        token: String,
+        position: usize,
+    },
+    UnexpectedEnd,
+}
+
+impl Display for ExprError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExprError::Unexpected { token, position } => {
+                write!(f, "unexpected {token:?} at position {position}")
+            }
+            ExprError::UnexpectedEnd => write!(f, "expression ended early"),
+        }
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+/// Recursive descent over `expr := term (('+' | '-') term)*`,
+/// `term := factor (('*' | '/' | '%') factor)*` and `factor := number | old | '(' expr ')'`
+struct ExprParser<'a> {
+    tokens: Vec<(usize, &'a str)>,
+    pos: usize,
+}
+
+impl<'a> ExprParser<'a> {
+    fn new(text: &'a str) -> Self {
+        let mut tokens = Vec::new();
+        let mut chars = text.char_indices().peekable();
+        while let Some((start, chr)) = chars.next() {
+            if chr.is_whitespace() {
+                continue;
+            }
+            let mut end = start + chr.len_utf8();
+            if chr.is_alphanumeric() {
+                // numbers and names run until the next operator, paren or space
+                while let Some((i, c)) = chars.next_if(|(_, c)| c.is_alphanumeric()) {
+                    end = i + c.len_utf8();
+                }
+            }
+            tokens.push((start, &text[start..end]));
+        }
+
+        Self { tokens, pos: 0 }
+    }
+
+    fn next(&mut self) -> Result<(usize, &'a str), ExprError> {
+        let token = self.tokens.get(self.pos).ok_or(ExprError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(*token)
+    }
+
+    /// Consume the next token if it is one of `ops`
+    fn next_op(&mut self, ops: &[&str]) -> Option<&'a str> {
+        let (_, token) = self.tokens.get(self.pos)?;
+        if ops.contains(token) {
+            self.pos += 1;
+            Some(token)
+        } else {
+            None
+        }
+    }
+
+    fn expr(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.term()?;
+        while let Some(op) = self.next_op(&["+", "-"]) {
+            let pair = (Box::new(node), Box::new(self.term()?));
+            node = match op {
+                "+" => Node::Add(pair),
+                _ => Node::Sub(pair),
+            };
+        }
+
+        Ok(node)
+    }
+
+    fn term(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.factor()?;
+        while let Some(op) = self.next_op(&["*", "/", "%"]) {
+            let pair = (Box::new(node), Box::new(self.factor()?));
+            node = match op {
+                "*" => Node::Mul(pair),
+                "/" => Node::Div(pair),
+                _ => Node::Rem(pair),
+            };
+        }
+
+        Ok(node)
+    }
+
+    fn factor(&mut self) -> Result<Node, ExprError> {
+        let (position, token) = self.next()?;
+        match token {
+            "old" => Ok(Node::Old),
+            "(" => {
+                let node = self.expr()?;
+                match self.next()? {
+                    (_, ")") => Ok(node),
+                    (position, token) => Err(ExprError::Unexpected {
+                        token: token.to_string(),
+                        position,
+                    }),
+                }
+            }
+            _ => token
+                .parse()
+                .map(Node::Const)
+                .map_err(|_| ExprError::Unexpected {
+                    token: token.to_string(),
+                    position,
+                }),
+        }
+    }
+}
+
+impl FromStr for Node {
+    type Err = ExprError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = ExprParser::new(s);
+        let node = parser.expr()?;
+        match parser.tokens.get(parser.pos) {
+            Some(&(position, token)) => Err(ExprError::Unexpected {
+                token: token.to_string(),
+                position,
+            }),
+            None => Ok(node),
         }
     }
 }
@@ -26,6 +242,31 @@ struct Monkey {
     false_target: usize,
 }
 
+impl Monkey {
+    /// The calmed down worry level of an inspected item and which monkey it's thrown to
+    fn inspect(
+        &self,
+        worry: usize,
+        decay: impl Fn(usize) -> usize,
+    ) -> Result<(usize, usize), EvalError> {
+        let worry = decay(self.operation.eval(worry)?);
+        let target = if worry.is_multiple_of(self.div_test) {
+            self.true_target
+        } else {
+            self.false_target
+        };
+
+        Ok((worry, target))
+    }
+}
+
+/// Whether an item thrown from one monkey to another is inspected again in the same round.
+/// Monkeys take turns in order and each keeps going until its queue is empty, so only throws to
+/// an earlier monkey wait for the next round.
+fn same_round(from: usize, to: usize) -> bool {
+    to >= from
+}
+
 fn parse_input(input: &[&str]) -> Vec<Monkey> {
     let mut monkeys = Vec::new();
 
@@ -44,15 +285,9 @@ fn parse_input(input: &[&str]) -> Vec<Monkey> {
 
         // Operation
         let (_, op_txt) = lines.next().unwrap().split_once('=').unwrap();
-        let mut parts = op_txt.split_whitespace();
-        let left: Node = parts.next().unwrap().into();
-        let op = parts.next().unwrap();
-        let right: Node = parts.next().unwrap().into();
-        let operation = match op {
-            "+" => Node::Add((Box::new(left), Box::new(right))),
-            "*" => Node::Mul((Box::new(left), Box::new(right))),
-            _ => panic!("invalid op {op}"),
-        };
+        let operation = op_txt
+            .parse()
+            .unwrap_or_else(|e| panic!("invalid operation {:?}: {e}", op_txt.trim()));
 
         // Test
         let (_, div_test) = lines.next().unwrap().split_once("by").unwrap();
@@ -82,161 +317,409 @@ fn parse_input(input: &[&str]) -> Vec<Monkey> {
     monkeys
 }
 
-fn solve1(mut input: Vec<Monkey>) -> u32 {
-    let mut monkey_counter = vec![0; input.len()];
+/// How worry levels calm down after each inspection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Relief {
+    /// Divide by this much, rounding down
+    Divide(usize),
+    /// Keep worry levels modulo the product of every test divisor
+    Modulus,
+    /// Keep exact worry levels, failing as soon as one no longer fits
+    Exact,
+}
 
-    for _round in 0..20 {
-        for monkey_id in 0..input.len() {
-            while !input[monkey_id].items.is_empty() {
-                monkey_counter[monkey_id] += 1;
-                let cur_item = input[monkey_id].items.pop_front();
-                let Some(cur_item) = cur_item else {
-                continue;
-            };
+impl Relief {
+    /// `Modulus` whenever it gives the same answer as `Exact`
+    fn for_monkeys(monkeys: &[Monkey]) -> Self {
+        if monkeys.iter().all(|m| m.operation.preserves_congruence()) {
+            Relief::Modulus
+        } else {
+            Relief::Exact
+        }
+    }
 
-                let mut worry = cur_item;
-                worry = match &input[monkey_id].operation {
-                    Node::Const(val) => *val,
-                    Node::Add((left, right)) => {
-                        let left = match **left {
-                            Node::Const(val) => val,
-                            Node::Add(_) => panic!(),
-                            Node::Mul(_) => panic!(),
-                            Node::Old => worry,
-                        };
-                        let right = match **right {
-                            Node::Const(val) => val,
-                            Node::Add(_) => panic!(),
-                            Node::Mul(_) => panic!(),
-                            Node::Old => worry,
-                        };
-                        left + right
-                    }
-                    Node::Mul((left, right)) => {
-                        let left = match **left {
-                            Node::Const(val) => val,
-                            Node::Add(_) => panic!(),
-                            Node::Mul(_) => panic!(),
-                            Node::Old => worry,
-                        };
-                        let right = match **right {
-                            Node::Const(val) => val,
-                            Node::Add(_) => panic!(),
-                            Node::Mul(_) => panic!(),
-                            Node::Old => worry,
-                        };
-                        left * right
-                    }
-                    Node::Old => todo!(),
-                };
-
-                // worry decay
-                worry /= 3;
-
-                if worry % input[monkey_id].div_test == 0 {
-                    let true_target = input[monkey_id].true_target;
-                    input[true_target].items.push_back(worry);
-                } else {
-                    let false_target = input[monkey_id].false_target;
-                    input[false_target].items.push_back(worry);
-                }
-            }
+    fn apply(&self, worry: usize, modulus: usize) -> usize {
+        match self {
+            Relief::Divide(by) => worry / by,
+            Relief::Modulus => worry % modulus,
+            Relief::Exact => worry,
         }
     }
-    monkey_counter.sort_unstable();
-    monkey_counter
+}
+
+impl FromStr for Relief {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mod" => Ok(Self::Modulus),
+            "exact" => Ok(Self::Exact),
+            _ => match s.parse() {
+                Ok(by) if by > 0 => Ok(Self::Divide(by)),
+                _ => Err(format!("unknown relief {s:?}")),
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SimError {
+    /// `Relief::Modulus` was asked for but this monkey's operation can't be reduced
+    UnsoundShortcut { monkey: usize },
+    Eval {
+        round: usize,
+        monkey: usize,
+        error: EvalError,
+    },
+}
+
+impl Display for SimError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimError::UnsoundShortcut { monkey } => write!(
+                f,
+                "monkey {monkey} subtracts or divides, so worry levels can't be reduced"
+            ),
+            SimError::Eval {
+                round,
+                monkey,
+                error,
+            } => write!(f, "round {round}, monkey {monkey}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SimError {}
+
+/// Product of every test divisor, the modulus `Relief::Modulus` keeps worry levels under
+fn test_modulus(monkeys: &[Monkey]) -> usize {
+    monkeys.iter().map(|m| m.div_test).product()
+}
+
+/// Fail unless reducing worry levels by the test modulus leaves every monkey's choices unchanged
+fn check_congruence(monkeys: &[Monkey]) -> Result<(), SimError> {
+    match monkeys
         .iter()
-        .rev()
-        .take(2)
-        .copied()
-        .reduce(|acc, i| acc * i)
-        .unwrap()
+        .position(|m| !m.operation.preserves_congruence())
+    {
+        Some(monkey) => Err(SimError::UnsoundShortcut { monkey }),
+        None => Ok(()),
+    }
+}
+
+/// Let every monkey take its turn once, counting inspections and calming worry with `decay`
+fn play_round(
+    monkeys: &mut [Monkey],
+    monkey_counter: &mut [u64],
+    round: usize,
+    decay: impl Fn(usize) -> usize,
+) -> Result<(), SimError> {
+    for monkey_id in 0..monkeys.len() {
+        while let Some(cur_item) = monkeys[monkey_id].items.pop_front() {
+            monkey_counter[monkey_id] += 1;
+
+            let (worry, target) =
+                monkeys[monkey_id]
+                    .inspect(cur_item, &decay)
+                    .map_err(|error| SimError::Eval {
+                        round,
+                        monkey: monkey_id,
+                        error,
+                    })?;
+            monkeys[target].items.push_back(worry);
+        }
+    }
+
+    Ok(())
 }
 
-fn solve2(mut input: Vec<Monkey>) -> usize {
-    let mut monkey_counter = vec![0; input.len()];
-    let big_div = input
+/// One configuration of the game: how many rounds to play, how worry calms down and how many
+/// of the busiest monkeys make up the monkey business
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Simulation {
+    rounds: usize,
+    relief: Relief,
+    top_k: usize,
+}
+
+impl Simulation {
+    fn part1() -> Self {
+        Self {
+            rounds: 20,
+            relief: Relief::Divide(3),
+            top_k: 2,
+        }
+    }
+
+    fn part2(monkeys: &[Monkey]) -> Self {
+        Self {
+            rounds: 10000,
+            relief: Relief::for_monkeys(monkeys),
+            top_k: 2,
+        }
+    }
+
+    fn with_rounds(mut self, rounds: usize) -> Self {
+        self.rounds = rounds;
+        self
+    }
+
+    fn with_relief(mut self, relief: Relief) -> Self {
+        self.relief = relief;
+        self
+    }
+
+    fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+
+    /// Play every round, handing the monkeys and inspection totals to `observe` after each one
+    fn play(
+        &self,
+        mut monkeys: Vec<Monkey>,
+        mut observe: impl FnMut(usize, &[Monkey], &[u64]),
+    ) -> Result<Vec<u64>, SimError> {
+        if self.relief == Relief::Modulus {
+            check_congruence(&monkeys)?;
+        }
+
+        let mut monkey_counter = vec![0; monkeys.len()];
+        let modulus = test_modulus(&monkeys);
+        for round in 1..=self.rounds {
+            play_round(&mut monkeys, &mut monkey_counter, round, |worry| {
+                self.relief.apply(worry, modulus)
+            })?;
+            observe(round, &monkeys, &monkey_counter);
+        }
+
+        Ok(monkey_counter)
+    }
+
+    /// Inspections per monkey once every round is played, skipping ahead with `fast_forward`
+    /// when worry levels are reduced
+    fn counts(&self, monkeys: Vec<Monkey>) -> Result<Vec<u64>, SimError> {
+        match self.relief {
+            Relief::Modulus => fast_forward(&monkeys, self.rounds),
+            _ => self.play(monkeys, |_, _, _| ()),
+        }
+    }
+
+    fn monkey_business(&self, monkeys: Vec<Monkey>) -> Result<u128, SimError> {
+        let counts = self.counts(monkeys)?;
+        Ok(monkey_business(&counts, self.top_k))
+    }
+
+    /// Where everything stands after each round
+    fn trace(&self, monkeys: Vec<Monkey>) -> Result<Vec<RoundTrace>, SimError> {
+        let mut traces = Vec::new();
+        self.play(monkeys, |round, monkeys, monkey_counter| {
+            traces.push(RoundTrace {
+                round,
+                inspections: monkey_counter.to_vec(),
+                items: monkeys
+                    .iter()
+                    .map(|m| m.items.iter().copied().collect())
+                    .collect(),
+            })
+        })?;
+
+        Ok(traces)
+    }
+}
+
+/// Inspection totals so far and the items each monkey holds at the end of a round
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RoundTrace {
+    round: usize,
+    inspections: Vec<u64>,
+    items: Vec<Vec<usize>>,
+}
+
+impl Display for RoundTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "== After round {} ==", self.round)?;
+        for (id, (inspections, items)) in self.inspections.iter().zip(&self.items).enumerate() {
+            let items: Vec<String> = items.iter().map(|i| i.to_string()).collect();
+            writeln!(
+                f,
+                "Monkey {id} ({inspections} inspections): {}",
+                items.join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Graphviz DOT of who throws to whom, optionally labelling each monkey with its inspections
+fn throw_graph(monkeys: &[Monkey], inspections: Option<&[u64]>) -> String {
+    let mut out = String::from("digraph monkeys {\n");
+    for (id, monkey) in monkeys.iter().enumerate() {
+        let mut label = format!(
+            "Monkey {id}\\nnew = {}\\ndivisible by {}",
+            monkey.operation, monkey.div_test
+        );
+        if let Some(inspections) = inspections {
+            label.push_str(&format!("\\ninspected {}", inspections[id]));
+        }
+        out.push_str(&format!("    {id} [label=\"{label}\"];\n"));
+        out.push_str(&format!(
+            "    {id} -> {} [label=\"true\"];\n",
+            monkey.true_target
+        ));
+        out.push_str(&format!(
+            "    {id} -> {} [label=\"false\", style=dashed];\n",
+            monkey.false_target
+        ));
+    }
+    out.push_str("}\n");
+
+    out
+}
+
+fn solve1(input: Vec<Monkey>) -> u128 {
+    Simulation::part1()
+        .monkey_business(input)
+        .unwrap_or_else(|e| panic!("{e}"))
+}
+
+fn solve2(input: Vec<Monkey>) -> u128 {
+    Simulation::part2(&input)
+        .monkey_business(input)
+        .unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Inspections per monkey after `rounds` rounds with reduced worry levels. Items never affect
+/// each other, so each one is followed on its own until it starts a round in a `(monkey, worry)`
+/// state it started an earlier round in, after which its rounds repeat.
+fn fast_forward(monkeys: &[Monkey], rounds: usize) -> Result<Vec<u64>, SimError> {
+    check_congruence(monkeys)?;
+
+    let modulus = test_modulus(monkeys);
+    let mut counts = vec![0; monkeys.len()];
+    for (first_monkey, item) in monkeys
         .iter()
-        .map(|i| i.div_test)
-        .reduce(|acc, i| acc * i)
-        .unwrap();
-    println!("{big_div}");
-
-    for _round in 0..10000 {
-        for monkey_id in 0..input.len() {
-            while !input[monkey_id].items.is_empty() {
-                monkey_counter[monkey_id] += 1;
-                let cur_item = input[monkey_id].items.pop_front();
-                let Some(cur_item) = cur_item else {
-                continue;
-            };
+        .enumerate()
+        .flat_map(|(id, m)| m.items.iter().map(move |item| (id, *item)))
+    {
+        let (mut monkey, mut worry) = (first_monkey, item % modulus);
+        let mut seen = HashMap::new();
+        // every monkey that inspected the item, and where in that list each round began
+        let mut visits = Vec::new();
+        let mut round_starts = Vec::new();
+        let mut cycle_start = None;
+        while round_starts.len() < rounds {
+            if let Some(&start) = seen.get(&(monkey, worry)) {
+                cycle_start = Some(start);
+                break;
+            }
+            seen.insert((monkey, worry), round_starts.len());
+            round_starts.push(visits.len());
+
+            loop {
+                visits.push(monkey);
+                let target;
+                (worry, target) = monkeys[monkey]
+                    .inspect(worry, |worry| Relief::Modulus.apply(worry, modulus))
+                    .map_err(|error| SimError::Eval {
+                        round: round_starts.len(),
+                        monkey,
+                        error,
+                    })?;
 
-                let mut worry = cur_item;
-                worry = match &input[monkey_id].operation {
-                    Node::Const(val) => *val,
-                    Node::Add((left, right)) => {
-                        let left = match **left {
-                            Node::Const(val) => val,
-                            Node::Add(_) => panic!(),
-                            Node::Mul(_) => panic!(),
-                            Node::Old => worry,
-                        };
-                        let right = match **right {
-                            Node::Const(val) => val,
-                            Node::Add(_) => panic!(),
-                            Node::Mul(_) => panic!(),
-                            Node::Old => worry,
-                        };
-                        left + right
-                    }
-                    Node::Mul((left, right)) => {
-                        let left = match **left {
-                            Node::Const(val) => val,
-                            Node::Add(_) => panic!(),
-                            Node::Mul(_) => panic!(),
-                            Node::Old => worry,
-                        };
-                        let right = match **right {
-                            Node::Const(val) => val,
-                            Node::Add(_) => panic!(),
-                            Node::Mul(_) => panic!(),
-                            Node::Old => worry,
-                        };
-                        left * right
-                    }
-                    Node::Old => todo!(),
-                };
-
-                // worry decay
-                worry %= big_div;
-
-                if worry % input[monkey_id].div_test == 0 {
-                    let true_target = input[monkey_id].true_target;
-                    input[true_target].items.push_back(worry);
-                } else {
-                    let false_target = input[monkey_id].false_target;
-                    input[false_target].items.push_back(worry);
+                let next_round = !same_round(monkey, target);
+                monkey = target;
+                if next_round {
+                    break;
                 }
             }
         }
+
+        for &visited in &visits {
+            counts[visited] += 1;
+        }
+        let Some(start) = cycle_start else {
+            continue;
+        };
+        let cycle_len = round_starts.len() - start;
+        let remaining = rounds - round_starts.len();
+        let visits_from =
+            |round: usize| &visits[round_starts.get(round).map_or(visits.len(), |&v| v)..];
+        for &visited in visits_from(start) {
+            counts[visited] += (remaining / cycle_len) as u64;
+        }
+        let partial = visits_from(start).len() - visits_from(start + remaining % cycle_len).len();
+        for &visited in &visits_from(start)[..partial] {
+            counts[visited] += 1;
+        }
     }
 
-    monkey_counter.sort_unstable();
-    monkey_counter
+    Ok(counts)
+}
+
+/// Product of the `top_k` highest inspection counts
+fn monkey_business(counts: &[u64], top_k: usize) -> u128 {
+    let mut counts = counts.to_vec();
+    counts.sort_unstable();
+    counts
         .iter()
         .rev()
-        .take(2)
-        .copied()
-        .reduce(|acc, i| acc * i)
-        .unwrap()
+        .take(top_k)
+        .map(|&c| c as u128)
+        .product()
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    std::env::args().skip_while(|a| a != name).nth(1)
 }
 
 fn main() {
     let input: Vec<&str> = include_str!("input.txt").lines().collect();
     let input = parse_input(&input);
 
+    // `--verbose` explains how part 2 keeps worry levels small
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--verbose") {
+        println!("test modulus: {}", test_modulus(&input));
+        println!("part 2 relief: {:?}", Relief::for_monkeys(&input));
+    }
+
     println!("part 1: {}", solve1(input.clone()));
-    println!("part 2: {}", solve2(input));
+    println!("part 2: {}", solve2(input.clone()));
+
+    // other games run with `--rounds N [--relief N|mod|exact] [--top K]`, adding `--trace` prints
+    // everything after each round and `--dot FILE` saves the throw graph with the final totals.
+    // Relief defaults to part 2's, and reduced worry levels skip rounds ahead so N can be huge.
+    let Some(rounds) = arg_value("--rounds") else {
+        return;
+    };
+    let mut simulation = Simulation::part2(&input)
+        .with_rounds(rounds.parse().unwrap())
+        .with_top_k(arg_value("--top").map_or(2, |k| k.parse().unwrap()));
+    if let Some(relief) = arg_value("--relief") {
+        simulation = simulation.with_relief(relief.parse().unwrap());
+    }
+    if args.iter().any(|a| a == "--trace") {
+        match simulation.trace(input.clone()) {
+            Ok(traces) => traces.iter().for_each(|round| print!("{round}")),
+            Err(e) => println!("{e}"),
+        }
+    }
+    let counts = match simulation.counts(input.clone()) {
+        Ok(counts) => counts,
+        Err(e) => {
+            println!("after {rounds} rounds: {e}");
+            return;
+        }
+    };
+    println!(
+        "after {rounds} rounds: {}",
+        monkey_business(&counts, simulation.top_k)
+    );
+    if let Some(path) = arg_value("--dot") {
+        std::fs::write(path, throw_graph(&input, Some(&counts))).unwrap();
+    }
 }
 
 #[cfg(test)]
@@ -284,4 +767,193 @@ mod tests {
         let parsed = parse_input(INPUT);
         assert_eq!(solve2(parsed), 2713310158)
     }
+
+    #[test]
+    fn test_expressions() {
+        let node: Node = "(old + 3) * old".parse().unwrap();
+        assert_eq!(node.eval(4), Ok(28));
+        assert_eq!(
+            node,
+            Node::Mul((
+                Box::new(Node::Add((Box::new(Node::Old), Box::new(Node::Const(3))))),
+                Box::new(Node::Old)
+            ))
+        );
+
+        // usual precedence, with operators of the same level applied left to right
+        let node: Node = "old + 2 * 3 - 10 % 4".parse().unwrap();
+        assert_eq!(node.eval(5), Ok(9));
+        let node: Node = "old / 2 / 3 * ((old))".parse().unwrap();
+        assert_eq!(node.eval(13), Ok(26));
+
+        assert_eq!("old * (2 + ".parse::<Node>(), Err(ExprError::UnexpectedEnd));
+        assert_eq!(
+            "old ^ 2".parse::<Node>(),
+            Err(ExprError::Unexpected {
+                token: "^".to_string(),
+                position: 4
+            })
+        );
+        assert_eq!(
+            "(old + 1))".parse::<Node>(),
+            Err(ExprError::Unexpected {
+                token: ")".to_string(),
+                position: 9
+            })
+        );
+
+        // the same monkeys written with redundant parentheses behave identically
+        let mut input = INPUT.to_vec();
+        input[16] = "  Operation: new = (old) * (old)";
+        assert_eq!(solve1(parse_input(&input)), 10605);
+    }
+
+    #[test]
+    fn test_fast_forward() {
+        let monkeys = parse_input(INPUT);
+        let counts = fast_forward(&monkeys, 10000).unwrap();
+        assert_eq!(counts, vec![52166, 47830, 1938, 52013]);
+        assert_eq!(monkey_business(&counts, 2), 2713310158);
+        assert_eq!(fast_forward(&monkeys, 20).unwrap(), vec![99, 97, 8, 103]);
+        let counts = fast_forward(&monkeys, 1_000_000_000_000).unwrap();
+        assert_eq!(
+            counts,
+            vec![5217653508757, 4782346491239, 193256578955, 5202028508760]
+        );
+
+        let mut input = INPUT.to_vec();
+        input[2] = "  Operation: new = old / 19";
+        assert_eq!(
+            fast_forward(&parse_input(&input), 1),
+            Err(SimError::UnsoundShortcut { monkey: 0 })
+        );
+    }
+
+    #[test]
+    fn test_self_throw() {
+        // monkey 1 keeps items that fail its test, inspecting them again in the same turn
+        let mut input = INPUT.to_vec();
+        input[12] = "    If false: throw to monkey 1";
+        let monkeys = parse_input(&input);
+        for rounds in [1, 20, 1000] {
+            let simulation = Simulation::part2(&monkeys).with_rounds(rounds);
+            assert_eq!(
+                fast_forward(&monkeys, rounds).unwrap(),
+                simulation.play(monkeys.clone(), |_, _, _| ()).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_trace() {
+        let traces = Simulation::part1().trace(parse_input(INPUT)).unwrap();
+        assert_eq!(
+            traces[0].to_string(),
+            "== After round 1 ==\n\
+             Monkey 0 (2 inspections): 20, 23, 27, 26\n\
+             Monkey 1 (4 inspections): 2080, 25, 167, 207, 401, 1046\n\
+             Monkey 2 (3 inspections): \n\
+             Monkey 3 (5 inspections): \n"
+        );
+        assert_eq!(traces[19].inspections, vec![101, 95, 7, 105]);
+        assert_eq!(traces[19].items[0], vec![10, 12, 14, 26, 34]);
+
+        let monkeys = parse_input(INPUT);
+        let dot = throw_graph(&monkeys[..1], None);
+        assert_eq!(
+            dot,
+            "digraph monkeys {\n    \
+             0 [label=\"Monkey 0\\nnew = old * 19\\ndivisible by 23\"];\n    \
+             0 -> 2 [label=\"true\"];\n    \
+             0 -> 3 [label=\"false\", style=dashed];\n\
+             }\n"
+        );
+        assert!(throw_graph(&monkeys, Some(&traces[19].inspections))
+            .contains("3 [label=\"Monkey 3\\nnew = old + 3\\ndivisible by 17\\ninspected 105\"]"));
+
+        let node: Node = "(old + 3) * (old - 2 * old)".parse().unwrap();
+        assert_eq!(node.to_string(), "(old + 3) * (old - (2 * old))");
+    }
+
+    #[test]
+    fn test_simulation() {
+        let monkeys = parse_input(INPUT);
+        let part1 = Simulation::part1();
+        assert_eq!(
+            part1.play(monkeys.clone(), |_, _, _| ()).unwrap(),
+            vec![101, 95, 7, 105]
+        );
+        assert_eq!(
+            part1.with_top_k(3).monkey_business(monkeys.clone()),
+            Ok(101 * 95 * 105)
+        );
+        assert_eq!(part1.with_top_k(0).monkey_business(monkeys.clone()), Ok(1));
+
+        // part 2 is the same game with more rounds and no division
+        let part2 = part1.with_rounds(10000).with_relief(Relief::Modulus);
+        assert_eq!(part2, Simulation::part2(&monkeys));
+        let counts = part2.with_rounds(1000).play(monkeys.clone(), |_, _, _| ());
+        assert_eq!(counts.unwrap(), vec![5204, 4792, 199, 5192]);
+        assert_eq!(
+            part2.with_rounds(1000).counts(monkeys.clone()),
+            Ok(vec![5204, 4792, 199, 5192])
+        );
+        // reduced worry levels skip ahead, so any number of rounds is cheap
+        assert_eq!(
+            part2
+                .with_rounds(1_000_000_000_000)
+                .monkey_business(monkeys.clone()),
+            Ok(5217653508757 * 5202028508760)
+        );
+
+        // dividing by one keeps exact levels, which agree with the reduced ones until they overflow
+        assert_eq!(
+            part1
+                .with_relief(Relief::Divide(1))
+                .with_rounds(12)
+                .monkey_business(monkeys.clone()),
+            part2.with_rounds(12).monkey_business(monkeys)
+        );
+
+        assert_eq!("4".parse(), Ok(Relief::Divide(4)));
+        assert_eq!("mod".parse(), Ok(Relief::Modulus));
+        assert_eq!("exact".parse(), Ok(Relief::Exact));
+        assert!("0".parse::<Relief>().is_err());
+    }
+
+    #[test]
+    fn test_worry_modes() {
+        let monkeys = parse_input(INPUT);
+        assert_eq!(Relief::for_monkeys(&monkeys), Relief::Modulus);
+        assert_eq!(
+            Simulation::part2(&monkeys)
+                .with_relief(Relief::Exact)
+                .monkey_business(monkeys.clone()),
+            Err(SimError::Eval {
+                round: 13,
+                monkey: 0,
+                error: EvalError::Overflow {
+                    left: 13988703546165100909,
+                    op: '*',
+                    right: 19
+                }
+            })
+        );
+
+        let mut input = INPUT.to_vec();
+        input[9] = "  Operation: new = old - 6";
+        let monkeys = parse_input(&input);
+        assert_eq!(Relief::for_monkeys(&monkeys), Relief::Exact);
+        assert_eq!(
+            Simulation::part2(&monkeys)
+                .with_relief(Relief::Modulus)
+                .monkey_business(monkeys),
+            Err(SimError::UnsoundShortcut { monkey: 1 })
+        );
+
+        let node: Node = "old - 7".parse().unwrap();
+        assert_eq!(node.eval(3), Err(EvalError::Negative { left: 3, right: 7 }));
+        let node: Node = "old % (old - 3)".parse().unwrap();
+        assert_eq!(node.eval(3), Err(EvalError::DivideByZero));
+    }
 }
@@ -1,6 +1,8 @@
-use std::collections::{HashSet, VecDeque};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::str::FromStr;
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Coord {
     x: usize,
     y: usize,
@@ -12,6 +14,178 @@ struct Map {
     end: Coord,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Bfs,
+    Dijkstra,
+    /// Dijkstra guided by a lower bound on the steps still needed
+    AStar,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bfs" => Ok(Self::Bfs),
+            "dijkstra" => Ok(Self::Dijkstra),
+            "astar" => Ok(Self::AStar),
+            _ => Err(format!("unknown algorithm {s:?}")),
+        }
+    }
+}
+
+/// Which way a search walks: `Up` follows the climbing rule from the start, `Down` retraces it
+/// backwards from the summit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Climb {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Goal {
+    Cell(Coord),
+    /// Any cell of this height
+    Height(u8),
+}
+
+impl Map {
+    fn height(&self, pos: Coord) -> u8 {
+        self.grid[pos.y][pos.x]
+    }
+
+    fn neighbors(&self, pos: Coord) -> impl Iterator<Item = Coord> + '_ {
+        [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .into_iter()
+            .map(move |(x, y)| (x + pos.x as isize, y + pos.y as isize))
+            .filter(|(x, y)| {
+                *x >= 0
+                    && *y >= 0
+                    && *x < self.grid[0].len() as isize
+                    && *y < self.grid.len() as isize
+            })
+            .map(|(x, y)| Coord {
+                x: x as usize,
+                y: y as usize,
+            })
+    }
+
+    /// The cells reachable in one step from `pos`
+    fn moves(&self, pos: Coord, climb: Climb) -> impl Iterator<Item = Coord> + '_ {
+        let cur_height = self.height(pos);
+        self.neighbors(pos).filter(move |c| match climb {
+            Climb::Up => self.height(*c) <= cur_height + 1,
+            Climb::Down => self.height(*c) + 1 >= cur_height,
+        })
+    }
+
+    fn is_goal(&self, pos: Coord, goal: Goal) -> bool {
+        match goal {
+            Goal::Cell(cell) => pos == cell,
+            Goal::Height(height) => self.height(pos) == height,
+        }
+    }
+
+    /// Fewest steps `pos` could still be from `goal`. Each step moves one cell and changes the
+    /// height by at most one in the direction of travel, so this never overestimates.
+    fn lower_bound(&self, pos: Coord, goal: Goal, climb: Climb) -> usize {
+        let target_height = match goal {
+            Goal::Cell(cell) => self.height(cell),
+            Goal::Height(height) => height,
+        };
+        let height_steps = match climb {
+            Climb::Up => target_height.saturating_sub(self.height(pos)),
+            Climb::Down => self.height(pos).saturating_sub(target_height),
+        } as usize;
+
+        match goal {
+            Goal::Cell(cell) => height_steps.max(pos.x.abs_diff(cell.x) + pos.y.abs_diff(cell.y)),
+            Goal::Height(_) => height_steps,
+        }
+    }
+
+    fn shortest_path(
+        &self,
+        start: Coord,
+        goal: Goal,
+        climb: Climb,
+        algorithm: Algorithm,
+    ) -> Option<usize> {
+        match algorithm {
+            Algorithm::Bfs => self.bfs(start, goal, climb),
+            Algorithm::Dijkstra => self.best_first(start, goal, climb, |_| 0),
+            Algorithm::AStar => {
+                self.best_first(start, goal, climb, |pos| self.lower_bound(pos, goal, climb))
+            }
+        }
+    }
+
+    fn bfs(&self, start: Coord, goal: Goal, climb: Climb) -> Option<usize> {
+        let mut dist = vec![vec![usize::MAX; self.grid[0].len()]; self.grid.len()];
+        let mut queue = VecDeque::from([start]);
+        dist[start.y][start.x] = 0;
+
+        while let Some(cur_pos) = queue.pop_front() {
+            let dist_to_cur = dist[cur_pos.y][cur_pos.x];
+            if self.is_goal(cur_pos, goal) {
+                return Some(dist_to_cur);
+            }
+
+            for neighbor in self.moves(cur_pos, climb) {
+                if dist[neighbor.y][neighbor.x] == usize::MAX {
+                    dist[neighbor.y][neighbor.x] = dist_to_cur + 1;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Dijkstra on a binary heap ordered by distance plus `estimate`, which makes it A* whenever
+    /// the estimate is nonzero. The estimate has to be consistent for the first visit to a cell
+    /// to be its shortest.
+    fn best_first(
+        &self,
+        start: Coord,
+        goal: Goal,
+        climb: Climb,
+        estimate: impl Fn(Coord) -> usize,
+    ) -> Option<usize> {
+        let mut dist = vec![vec![usize::MAX; self.grid[0].len()]; self.grid.len()];
+        let mut heap = BinaryHeap::new();
+        dist[start.y][start.x] = 0;
+        heap.push(Reverse((estimate(start), 0, start.y, start.x)));
+
+        while let Some(Reverse((_, dist_to_cur, y, x))) = heap.pop() {
+            let cur_pos = Coord { x, y };
+            if dist_to_cur > dist[y][x] {
+                // already reached more cheaply
+                continue;
+            }
+            if self.is_goal(cur_pos, goal) {
+                return Some(dist_to_cur);
+            }
+
+            for neighbor in self.moves(cur_pos, climb) {
+                let new_dist = dist_to_cur + 1;
+                if new_dist < dist[neighbor.y][neighbor.x] {
+                    dist[neighbor.y][neighbor.x] = new_dist;
+                    heap.push(Reverse((
+                        new_dist + estimate(neighbor),
+                        new_dist,
+                        neighbor.y,
+                        neighbor.x,
+                    )));
+                }
+            }
+        }
+
+        None
+    }
+}
+
 fn letter_to_height(letter: u8) -> u8 {
     assert!((97..=122).contains(&letter));
     letter - 97
@@ -45,131 +219,53 @@ fn parse_input(input: &[&str]) -> Map {
     Map { grid, start, end }
 }
 
-fn solve1(input: &[&str]) -> usize {
+fn solve1_with(input: &[&str], algorithm: Algorithm) -> Option<usize> {
     let map = parse_input(input);
 
-    let mut dist = vec![vec![usize::MAX; map.grid[0].len()]; map.grid.len()];
-    let mut queue = VecDeque::new();
-    for y in 0..map.grid.len() {
-        for x in 0..map.grid[0].len() {
-            queue.push_back(Coord { x, y });
-        }
-    }
-    dist[map.start.y][map.start.x] = 0;
-
-    while !queue.is_empty() {
-        queue
-            .make_contiguous()
-            .sort_unstable_by_key(|i| dist[i.y][i.x]);
-        let cur_pos = queue.pop_front().unwrap();
-        if cur_pos == map.end {
-            break;
-        }
-        let dist_to_cur = dist[cur_pos.y][cur_pos.x];
-
-        let cur_height = map.grid[cur_pos.y][cur_pos.x];
-
-        for neighbor in [(-1, 0), (1, 0), (0, -1), (0, 1)]
-            .into_iter()
-            .map(|(x, y)| (x + cur_pos.x as isize, y + cur_pos.y as isize))
-            .filter(|(x, y)| {
-                *x >= 0
-                    && *y >= 0
-                    && *x < map.grid[0].len() as isize
-                    && *y < map.grid.len() as isize
-            })
-            .map(|(x, y)| Coord {
-                x: x as usize,
-                y: y as usize,
-            })
-            .filter(|c| {
-                map.grid[c.y][c.x] <= cur_height + 1 && queue.iter().any(|coord| *coord == *c)
-            })
-        {
-            let prev_dist = dist[neighbor.y][neighbor.x];
-
-            let new_dist = dist_to_cur + 1;
-            if new_dist < prev_dist {
-                dist[neighbor.y][neighbor.x] = new_dist;
-            }
-        }
-    }
-
-    dist[map.end.y][map.end.x]
+    map.shortest_path(map.start, Goal::Cell(map.end), Climb::Up, algorithm)
 }
 
-fn solve2(input: &[&str]) -> usize {
+/// Walking down from the summit finds the closest of all the lowest cells in a single search
+fn solve2_with(input: &[&str], algorithm: Algorithm) -> Option<usize> {
     let map = parse_input(input);
 
-    let mut end_positions = HashSet::new();
-    for (y, row) in map.grid.iter().enumerate() {
-        for (x, col) in row.iter().enumerate() {
-            if *col == 0 {
-                end_positions.insert(Coord { x, y });
-            }
-        }
-    }
-    let mut shortest_path = usize::MAX;
-
-    let mut dist = vec![vec![usize::MAX; map.grid[0].len()]; map.grid.len()];
-    let mut queue = VecDeque::new();
-    for y in 0..map.grid.len() {
-        for x in 0..map.grid[0].len() {
-            queue.push_back(Coord { x, y });
-        }
-    }
-    dist[map.end.y][map.end.x] = 0;
-
-    while !queue.is_empty() && !end_positions.is_empty() {
-        queue
-            .make_contiguous()
-            .sort_unstable_by_key(|i| dist[i.y][i.x]);
-        let cur_pos = queue.pop_front().unwrap();
-
-        if end_positions.contains(&cur_pos) {
-            shortest_path = shortest_path.min(dist[cur_pos.y][cur_pos.x]);
-            end_positions.remove(&cur_pos);
-            continue;
-        }
-
-        let dist_to_cur = dist[cur_pos.y][cur_pos.x];
-        let cur_height = map.grid[cur_pos.y][cur_pos.x];
+    map.shortest_path(map.end, Goal::Height(0), Climb::Down, algorithm)
+}
 
-        for neighbor in [(-1, 0), (1, 0), (0, -1), (0, 1)]
-            .into_iter()
-            .map(|(x, y)| (x + cur_pos.x as isize, y + cur_pos.y as isize))
-            .filter(|(x, y)| {
-                *x >= 0
-                    && *y >= 0
-                    && *x < map.grid[0].len() as isize
-                    && *y < map.grid.len() as isize
-            })
-            .map(|(x, y)| Coord {
-                x: x as usize,
-                y: y as usize,
-            })
-            .filter(|c| {
-                map.grid[c.y][c.x] >= cur_height.saturating_sub(1)
-                    && queue.iter().any(|coord| *coord == *c)
-            })
-        {
-            let prev_dist = dist[neighbor.y][neighbor.x];
+fn solve1(input: &[&str]) -> usize {
+    solve1_with(input, Algorithm::Bfs).unwrap()
+}
 
-            let new_dist = dist_to_cur + 1;
-            if new_dist < prev_dist {
-                dist[neighbor.y][neighbor.x] = new_dist;
-            }
-        }
-    }
+fn solve2(input: &[&str]) -> usize {
+    solve2_with(input, Algorithm::Bfs).unwrap()
+}
 
-    shortest_path
+fn arg_value(name: &str) -> Option<String> {
+    std::env::args().skip_while(|a| a != name).nth(1)
 }
 
 fn main() {
     let input: Vec<&str> = include_str!("input.txt").lines().collect();
 
-    println!("part 1: {}", solve1(&input));
-    println!("part 2: {}", solve2(&input));
+    // other maps run with `--input FILE [--algorithm bfs|dijkstra|astar]`
+    let Some(path) = arg_value("--input") else {
+        println!("part 1: {}", solve1(&input));
+        println!("part 2: {}", solve2(&input));
+        return;
+    };
+    let contents = std::fs::read_to_string(path).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    let algorithm = arg_value("--algorithm").map_or(Algorithm::Bfs, |a| a.parse().unwrap());
+
+    for (part, steps) in [
+        (1, solve1_with(&lines, algorithm)),
+        (2, solve2_with(&lines, algorithm)),
+    ] {
+        match steps {
+            Some(steps) => println!("part {part}: {steps}"),
+            None => println!("part {part}: unreachable"),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -187,4 +283,43 @@ mod tests {
     fn test2() {
         assert_eq!(solve2(INPUT), 29)
     }
+
+    #[test]
+    fn test_algorithms() {
+        // a slope rising to the east, crossed by walls too high to climb with one gap each, so
+        // the shortest path zigzags far from a straight line
+        let mut rows = Vec::new();
+        for y in 0..26 {
+            let row: String = (0..40)
+                .map(|x| {
+                    let height = x * 25 / 39;
+                    match (x, y) {
+                        (0, 0) => 'S',
+                        (39, 25) => 'E',
+                        _ if x % 4 == 2 && (y + x / 4 * 7) % 26 != 0 => {
+                            (b'a' + (height + 2).min(25) as u8) as char
+                        }
+                        _ => (b'a' + height as u8) as char,
+                    }
+                })
+                .collect();
+            rows.push(row);
+        }
+        let map: Vec<&str> = rows.iter().map(|r| r.as_str()).collect();
+
+        for input in [INPUT, &map] {
+            for algorithm in [Algorithm::Dijkstra, Algorithm::AStar] {
+                assert_eq!(solve1_with(input, algorithm), Some(solve1(input)));
+                assert_eq!(solve2_with(input, algorithm), Some(solve2(input)));
+            }
+        }
+
+        // the start is stuck below a cliff, but the other lowest cell can still reach the summit
+        let input = &["SzabcdefghijklmnopqrstuvwxyzE"];
+        for algorithm in [Algorithm::Bfs, Algorithm::Dijkstra, Algorithm::AStar] {
+            assert_eq!(solve1_with(input, algorithm), None);
+            assert_eq!(solve2_with(input, algorithm), Some(26));
+        }
+        assert_eq!("astar".parse(), Ok(Algorithm::AStar));
+    }
 }